@@ -0,0 +1,27 @@
+create virtual table activities_fts using fts5(
+    description,
+    location,
+    type,
+    content = 'activities',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+insert into activities_fts(activities_fts) values ('rebuild');
+
+create trigger activities_fts_insert after insert on activities begin
+    insert into activities_fts(rowid, description, location, type)
+        values (new.id, new.description, new.location, new.type);
+end;
+
+create trigger activities_fts_delete after delete on activities begin
+    insert into activities_fts(activities_fts, rowid, description, location, type)
+        values ('delete', old.id, old.description, old.location, old.type);
+end;
+
+create trigger activities_fts_update after update on activities begin
+    insert into activities_fts(activities_fts, rowid, description, location, type)
+        values ('delete', old.id, old.description, old.location, old.type);
+    insert into activities_fts(rowid, description, location, type)
+        values (new.id, new.description, new.location, new.type);
+end;
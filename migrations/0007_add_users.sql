@@ -0,0 +1,19 @@
+create table users(
+    id integer primary key autoincrement,
+    username text not null unique collate nocase,
+    password_hash text not null,
+    created_at text not null
+);
+
+create table sessions(
+    token text primary key,
+    user_id integer not null references users(id) on delete cascade,
+    expires_at text not null
+);
+
+-- Activities logged before accounts existed have no owner. The first user to
+-- register claims them.
+alter table activities
+    add column owner_id integer references users(id);
+
+create index activities_owner_date on activities(owner_id, date);
@@ -0,0 +1,29 @@
+create table locations(
+    id integer primary key autoincrement,
+    owner_id integer references users(id) on delete cascade,
+    name text not null collate nocase,
+    latitude real,
+    longitude real,
+    elevation_m real,
+    country text not null default '',
+    notes text not null default '',
+    unique(owner_id, name)
+);
+
+-- Collect the free text locations, treating names that only differ in case
+-- or surrounding whitespace as the same place.
+update activities set location = trim(location) where location is not null;
+
+insert into locations (owner_id, name)
+    select owner_id, min(location)
+    from activities
+    where location != ''
+    group by owner_id, location collate nocase;
+
+update activities
+    set location = (
+        select l.name
+        from locations as l
+        where l.owner_id is activities.owner_id and l.name = activities.location
+    )
+    where location != '';
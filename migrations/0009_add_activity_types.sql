@@ -0,0 +1,25 @@
+create table activity_types(
+    id integer primary key autoincrement,
+    owner_id integer references users(id) on delete cascade,
+    name text not null collate nocase,
+    -- Hex colour like #A0B1C2. Types without one get a colour from the name.
+    color text,
+    icon text not null default '',
+    sort_order integer not null default 0,
+    archived boolean not null default false,
+    unique(owner_id, name)
+);
+
+update activities set type = trim(type);
+
+insert into activity_types (owner_id, name)
+    select owner_id, min(type)
+    from activities
+    group by owner_id, type collate nocase;
+
+update activities
+    set type = (
+        select t.name
+        from activity_types as t
+        where t.owner_id is activities.owner_id and t.name = activities.type
+    );
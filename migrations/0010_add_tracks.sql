@@ -0,0 +1,16 @@
+-- Metrics derived from an uploaded GPX or FIT track.
+alter table activities add column distance_km real;
+alter table activities add column vertical_drop_m real;
+alter table activities add column max_speed_kmh real;
+alter table activities add column runs integer;
+
+-- The uploaded file. Tracks are stored before the activity is saved, so the
+-- activity is empty until then.
+create table tracks(
+    id integer primary key autoincrement,
+    owner_id integer not null references users(id) on delete cascade,
+    activity_id integer unique references activities(id) on delete cascade,
+    filename text not null,
+    data blob not null,
+    uploaded_at text not null
+);
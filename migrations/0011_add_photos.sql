@@ -0,0 +1,13 @@
+-- Photos attached to an activity. The image itself and its thumbnail are
+-- stored in the data directory, named after `key`.
+create table photos(
+    id integer primary key autoincrement,
+    owner_id integer not null references users(id) on delete cascade,
+    activity_id integer not null references activities(id) on delete cascade,
+    key text not null unique,
+    filename text not null,
+    content_type text not null,
+    uploaded_at text not null
+);
+
+create index photos_activity on photos(activity_id);
@@ -0,0 +1,14 @@
+-- Named starting points for new activities, e.g. an after-work session at the
+-- local hill.
+create table entry_templates(
+    id integer primary key autoincrement,
+    owner_id integer not null references users(id) on delete cascade,
+    name text not null collate nocase,
+    type text not null default '',
+    location text not null default '',
+    duration_hours real,
+    description text not null default '',
+    -- The add form starts from this template unless another one is picked.
+    is_default boolean not null default false,
+    unique(owner_id, name)
+);
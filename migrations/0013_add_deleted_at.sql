@@ -0,0 +1,2 @@
+-- Deleted activities stay in the trash until they are restored or purged.
+alter table activities add column deleted_at text;
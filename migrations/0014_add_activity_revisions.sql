@@ -0,0 +1,17 @@
+-- Earlier versions of activities, written each time an activity is changed.
+create table activity_revisions(
+    id integer primary key autoincrement,
+    activity_id integer not null references activities(id) on delete cascade,
+    owner_id integer not null references users(id) on delete cascade,
+    -- Who made the change that replaced this version.
+    edited_by integer references users(id) on delete set null,
+    edited_at text not null,
+    date text not null,
+    location text not null default '',
+    duration_hours real,
+    type text not null,
+    description text not null default '',
+    score real
+);
+
+create index activity_revisions_activity_id on activity_revisions(activity_id);
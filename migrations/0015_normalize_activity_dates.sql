@@ -0,0 +1,5 @@
+-- Rows converted from epoch seconds use "T" between the date and the time,
+-- while sqlx writes a space. Store them all the way sqlx does, so that dates
+-- compare and sort as text and the (owner_id, date) index can be used.
+update activities set date = replace(date, 'T', ' ') where date like '____-__-__T%';
+update activity_revisions set date = replace(date, 'T', ' ') where date like '____-__-__T%';
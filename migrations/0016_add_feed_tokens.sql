@@ -0,0 +1,7 @@
+-- A secret token in the calendar feed URL, since calendar apps can't log in.
+-- It is created when the user first opens the settings page, and can be
+-- replaced there to stop old URLs from working.
+alter table users
+    add column feed_token text;
+
+create unique index users_feed_token on users(feed_token);
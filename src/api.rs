@@ -0,0 +1,293 @@
+use axum::async_trait;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRequestParts, Path, Query, State};
+use axum::http::header::WWW_AUTHENTICATE;
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::NaiveDateTime;
+use serde_json::json;
+
+use crate::auth::authenticate;
+use crate::error::AppError;
+use crate::models::{
+    delete_activity, get_activities_from, get_activity, insert_activity, update_activity, Activity,
+    ActivityFilter, User,
+};
+use crate::AppState;
+
+/// Same as [`AppError`], but rendered as a JSON body instead of an HTML page.
+pub struct ApiError(AppError);
+
+type ApiResult<T> = Result<T, ApiError>;
+
+impl<E: Into<AppError>> From<E> for ApiError {
+    fn from(value: E) -> Self {
+        ApiError(value.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        self.0.log();
+        let body = json!({ "error": self.0.message() });
+        let mut response = (self.0.status(), Json(body)).into_response();
+        if let AppError::Unauthorized = self.0 {
+            response.headers_mut().insert(
+                WWW_AUTHENTICATE,
+                "Basic realm=\"snowlog\"".parse().expect("Valid header"),
+            );
+        }
+        response
+    }
+}
+
+/// The logged in user, but rejected with a JSON 401 instead of a redirect to
+/// the login page.
+pub struct ApiUser(User);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ApiUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> ApiResult<Self> {
+        match authenticate(state, &parts.headers).await? {
+            Some(user) => Ok(ApiUser(user)),
+            None => Err(AppError::Unauthorized.into()),
+        }
+    }
+}
+
+async fn find_activity(state: &AppState, user: &User, id: i64) -> ApiResult<Activity> {
+    get_activity(&state.pool, user.id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Activity {} does not exist", id)).into())
+}
+
+#[derive(serde::Deserialize)]
+pub struct ListQuery {
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+}
+
+pub async fn list_activities(
+    State(state): State<AppState>,
+    ApiUser(user): ApiUser,
+    Query(q): Query<ListQuery>,
+) -> ApiResult<Json<Vec<Activity>>> {
+    let activities = get_activities_from(
+        &state.pool,
+        user.id,
+        q.from,
+        q.to,
+        &ActivityFilter::default(),
+        None,
+    )
+    .await?;
+    Ok(Json(activities))
+}
+
+pub async fn get_activity_by_id(
+    State(state): State<AppState>,
+    ApiUser(user): ApiUser,
+    Path(id): Path<i64>,
+) -> ApiResult<Json<Activity>> {
+    Ok(Json(find_activity(&state, &user, id).await?))
+}
+
+pub async fn create_activity(
+    State(state): State<AppState>,
+    ApiUser(user): ApiUser,
+    body: Result<Json<Activity>, JsonRejection>,
+) -> ApiResult<(StatusCode, Json<Activity>)> {
+    let Json(mut activity) = body?;
+    activity.validate(state.settings.local_now())?;
+    activity.id = None;
+    let id = insert_activity(&state.pool, user.id, activity).await?;
+    let created = find_activity(&state, &user, id).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+pub async fn put_activity(
+    State(state): State<AppState>,
+    ApiUser(user): ApiUser,
+    Path(id): Path<i64>,
+    body: Result<Json<Activity>, JsonRejection>,
+) -> ApiResult<Json<Activity>> {
+    let Json(mut activity) = body?;
+    activity.validate(state.settings.local_now())?;
+    find_activity(&state, &user, id).await?;
+    activity.id = Some(id);
+    update_activity(&state.pool, user.id, activity, state.settings.local_now()).await?;
+    Ok(Json(find_activity(&state, &user, id).await?))
+}
+
+pub async fn remove_activity(
+    State(state): State<AppState>,
+    ApiUser(user): ApiUser,
+    Path(id): Path<i64>,
+) -> ApiResult<StatusCode> {
+    find_activity(&state, &user, id).await?;
+    delete_activity(&state.pool, user.id, id, state.settings.local_now()).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::header::{CONTENT_TYPE, COOKIE};
+    use axum::http::Request;
+    use axum::routing::get;
+    use axum::Router;
+    use serde_json::Value;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::assets::AssetSource;
+    use crate::models::{create_session, create_user};
+    use crate::photos::PhotoStore;
+    use crate::settings::Settings;
+    use crate::templates::CachedEnvironment;
+
+    async fn setup() -> Router {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        let owner = create_user(&pool, "alice", "hash", NaiveDateTime::default())
+            .await
+            .unwrap();
+        let expires = "2100-01-01T00:00:00".parse().unwrap();
+        create_session(&pool, owner, "alice-token", expires)
+            .await
+            .unwrap();
+
+        let templates = AssetSource::new(None, "ui/jinja");
+        let state = AppState {
+            pool,
+            environment: Box::leak(Box::new(CachedEnvironment::new(templates, false))),
+            settings: Arc::new(Settings::default()),
+            photos: Arc::new(PhotoStore::new(&std::env::temp_dir().join("snowlog-api")).unwrap()),
+        };
+        Router::new()
+            .route(
+                "/api/v1/activities",
+                get(list_activities).post(create_activity),
+            )
+            .route(
+                "/api/v1/activities/:id",
+                get(get_activity_by_id)
+                    .put(put_activity)
+                    .delete(remove_activity),
+            )
+            .with_state(state)
+    }
+
+    async fn send(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Option<Value>) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(COOKIE, "snowlog_session=alice-token")
+            .header(CONTENT_TYPE, "application/json");
+        let body = body.map(|x| Body::from(x.to_string())).unwrap_or_default();
+        let response = app
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&bytes).ok())
+    }
+
+    #[tokio::test]
+    async fn activities_round_trip_through_the_api() {
+        let app = setup().await;
+        let activity = json!({
+            "date": "2025-01-05T10:00:00",
+            "location": "Tryvann",
+            "duration_hours": 2.5,
+            "type": "Skis",
+            "score": 0.75,
+            "description": "Powder",
+        });
+
+        let (status, created) = send(&app, "POST", "/api/v1/activities", Some(activity)).await;
+        assert_eq!(StatusCode::CREATED, status);
+        let created = created.unwrap();
+        let id = created["id"].as_i64().unwrap();
+        assert_eq!("Tryvann", created["location"]);
+        assert_eq!(2.5, created["duration_hours"]);
+
+        let uri = format!("/api/v1/activities/{}", id);
+        let mut changed = created.clone();
+        changed["location"] = "Hemsedal".into();
+        let (status, updated) = send(&app, "PUT", &uri, Some(changed)).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("Hemsedal", updated.unwrap()["location"]);
+
+        let (status, list) = send(&app, "GET", "/api/v1/activities", None).await;
+        assert_eq!(StatusCode::OK, status);
+        let list = list.unwrap();
+        assert_eq!(1, list.as_array().unwrap().len());
+        assert_eq!("Hemsedal", list[0]["location"]);
+
+        let (status, _) = send(&app, "DELETE", &uri, None).await;
+        assert_eq!(StatusCode::NO_CONTENT, status);
+        let (status, body) = send(&app, "GET", &uri, None).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        assert!(body.unwrap()["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn errors_are_json_with_a_status() {
+        let app = setup().await;
+        let invalid = json!({
+            "date": "2025-01-05T10:00:00",
+            "location": "Tryvann",
+            "type": "Skis",
+            "description": "",
+        });
+        let (status, body) = send(&app, "POST", "/api/v1/activities", Some(invalid)).await;
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+        assert!(body.unwrap()["error"]
+            .as_str()
+            .unwrap()
+            .contains("Duration is required"));
+
+        let (status, _) = send(&app, "GET", "/api/v1/activities?from=soon", None).await;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+
+        let cases = [
+            (AppError::Unauthorized, StatusCode::UNAUTHORIZED),
+            (AppError::NotFound("Gone".into()), StatusCode::NOT_FOUND),
+            (AppError::BadRequest("Bad".into()), StatusCode::BAD_REQUEST),
+            (
+                AppError::Internal(anyhow::anyhow!("secret")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+        for (error, expected) in cases {
+            let message = error.message();
+            let response = ApiError(error).into_response();
+            assert_eq!(expected, response.status());
+            if expected == StatusCode::UNAUTHORIZED {
+                assert!(response.headers().contains_key(WWW_AUTHENTICATE));
+            }
+            let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let body: Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(json!({ "error": message }), body);
+        }
+    }
+}
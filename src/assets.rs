@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use axum::extract::Path;
+use axum::http::header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use rust_embed::RustEmbed;
+
+/// The page templates, compiled into the binary.
+#[derive(RustEmbed)]
+#[folder = "ui/jinja"]
+pub struct Templates;
+
+/// Everything under `/static`, compiled into the binary.
+#[derive(RustEmbed)]
+#[folder = "ui/static"]
+struct StaticFiles;
+
+#[cfg(debug_assertions)]
+fn use_embedded() -> bool {
+    false
+}
+#[cfg(not(debug_assertions))]
+fn use_embedded() -> bool {
+    true
+}
+
+/// Where templates or static files are read from.
+#[derive(Debug, Clone)]
+pub enum AssetSource {
+    Disk(PathBuf),
+    Embedded,
+}
+
+impl AssetSource {
+    /// A configured directory is always used. Otherwise release builds use the
+    /// embedded files, while debug builds read `default_dir` so that changes
+    /// show up without a rebuild.
+    pub fn new(dir: Option<PathBuf>, default_dir: &str) -> Self {
+        match dir {
+            Some(dir) => AssetSource::Disk(dir),
+            None if use_embedded() => AssetSource::Embedded,
+            None => AssetSource::Disk(PathBuf::from(default_dir)),
+        }
+    }
+}
+
+pub async fn get_static(Path(path): Path<String>, headers: HeaderMap) -> Response {
+    let Some(file) = StaticFiles::get(&path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let etag = format!("\"{}\"", hex(&file.metadata.sha256_hash()));
+    if headers
+        .get(IF_NONE_MATCH)
+        .is_some_and(|x| x.as_bytes() == etag.as_bytes())
+    {
+        return StatusCode::NOT_MODIFIED.into_response();
+    }
+
+    let mime = mime_guess::from_path(&path).first_or_octet_stream();
+    (
+        [(CONTENT_TYPE, mime.to_string()), (ETAG, etag)],
+        file.data.into_owned(),
+    )
+        .into_response()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn static_files_are_embedded() {
+        let response = get_static(Path("css/main.css".to_owned()), HeaderMap::new()).await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("text/css", response.headers()[CONTENT_TYPE]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_NONE_MATCH, response.headers()[ETAG].clone());
+        let response = get_static(Path("css/main.css".to_owned()), headers).await;
+        assert_eq!(StatusCode::NOT_MODIFIED, response.status());
+
+        let response = get_static(Path("missing.css".to_owned()), HeaderMap::new()).await;
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        assert!(Templates::get("layout.html").is_some());
+    }
+}
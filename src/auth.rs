@@ -0,0 +1,282 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::async_trait;
+use axum::extract::rejection::FormRejection;
+use axum::extract::{FromRequestParts, State};
+use axum::http::header::{AUTHORIZATION, COOKIE, SET_COOKIE};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chrono::{Duration, Utc};
+use minijinja::context;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    create_session, create_user, delete_expired_sessions, delete_session, get_session_user,
+    get_user_credentials, User,
+};
+use crate::AppState;
+
+const SESSION_COOKIE: &str = "snowlog_session";
+const SESSION_DAYS: i64 = 30;
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_USERNAME_LENGTH: usize = 50;
+
+pub fn hash_password(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::encode_b64(uuid::Uuid::new_v4().as_bytes())
+        .map_err(|e| anyhow::anyhow!("Failed to create salt: {}", e))?;
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?;
+    Ok(hash.to_string())
+}
+
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// A random token for sessions and calendar feeds.
+pub fn new_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+fn session_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, token)| token)
+}
+
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let encoded = value.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(BASE64_STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    Some((username.to_owned(), password.to_owned()))
+}
+
+/// Finds the user from the session cookie or, for scripts, from HTTP basic
+/// authentication.
+pub async fn authenticate(state: &AppState, headers: &HeaderMap) -> AppResult<Option<User>> {
+    if let Some(token) = session_token(headers) {
+        let now = Utc::now().naive_utc();
+        if let Some(user) = get_session_user(&state.pool, token, now).await? {
+            return Ok(Some(user));
+        }
+    }
+    if let Some((username, password)) = basic_credentials(headers) {
+        if let Some(user) = get_user_credentials(&state.pool, &username).await? {
+            if verify_password(&password, &user.password_hash) {
+                return Ok(Some(User {
+                    id: user.id,
+                    username: user.username,
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for User {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> AppResult<Self> {
+        authenticate(state, &parts.headers)
+            .await?
+            .ok_or(AppError::Unauthorized)
+    }
+}
+
+fn session_cookie(token: &str, max_age: Duration, secure: bool) -> String {
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}{}",
+        SESSION_COOKIE,
+        token,
+        max_age.num_seconds(),
+        if secure { "; Secure" } else { "" }
+    )
+}
+
+async fn start_session(state: &AppState, user_id: i64) -> AppResult<Response> {
+    let now = Utc::now().naive_utc();
+    delete_expired_sessions(&state.pool, now).await?;
+
+    let token = new_token();
+    let max_age = Duration::days(SESSION_DAYS);
+    create_session(&state.pool, user_id, &token, now + max_age).await?;
+
+    Ok((
+        [(
+            SET_COOKIE,
+            session_cookie(&token, max_age, state.settings.secure_cookies),
+        )],
+        Redirect::to("/"),
+    )
+        .into_response())
+}
+
+#[derive(serde::Deserialize)]
+pub struct Credentials {
+    username: String,
+    password: String,
+}
+
+fn render_login(
+    state: &AppState,
+    register: bool,
+    username: &str,
+    error: Option<&str>,
+) -> Html<String> {
+    state.render(
+        "login.html",
+        context!(
+            register => register,
+            allow_registration => state.settings.allow_registration,
+            username => username,
+            error => error,
+        ),
+    )
+}
+
+pub async fn get_login(State(state): State<AppState>) -> Html<String> {
+    render_login(&state, false, "", None)
+}
+
+pub async fn post_login(
+    State(state): State<AppState>,
+    form: Result<Form<Credentials>, FormRejection>,
+) -> AppResult<Response> {
+    let Form(credentials) = form?;
+    let user = get_user_credentials(&state.pool, credentials.username.trim()).await?;
+    match user {
+        Some(user) if verify_password(&credentials.password, &user.password_hash) => {
+            start_session(&state, user.id).await
+        }
+        _ => {
+            let html = render_login(
+                &state,
+                false,
+                &credentials.username,
+                Some("Wrong username or password"),
+            );
+            Ok((StatusCode::UNAUTHORIZED, html).into_response())
+        }
+    }
+}
+
+pub async fn get_register(State(state): State<AppState>) -> AppResult<Html<String>> {
+    if !state.settings.allow_registration {
+        return Err(AppError::NotFound("Registration is disabled".to_owned()));
+    }
+    Ok(render_login(&state, true, "", None))
+}
+
+pub async fn post_register(
+    State(state): State<AppState>,
+    form: Result<Form<Credentials>, FormRejection>,
+) -> AppResult<Response> {
+    if !state.settings.allow_registration {
+        return Err(AppError::NotFound("Registration is disabled".to_owned()));
+    }
+    let Form(credentials) = form?;
+    let username = credentials.username.trim();
+
+    let error = if username.is_empty() || username.chars().count() > MAX_USERNAME_LENGTH {
+        Some(format!(
+            "The username must be between 1 and {} characters",
+            MAX_USERNAME_LENGTH
+        ))
+    } else if credentials.password.chars().count() < MIN_PASSWORD_LENGTH {
+        Some(format!(
+            "The password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        ))
+    } else if get_user_credentials(&state.pool, username).await?.is_some() {
+        Some("That username is taken".to_owned())
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        let html = render_login(&state, true, username, Some(&error));
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, html).into_response());
+    }
+
+    let hash = hash_password(&credentials.password)?;
+    let id = create_user(&state.pool, username, &hash, Utc::now().naive_utc()).await?;
+    start_session(&state, id).await
+}
+
+pub async fn post_logout(State(state): State<AppState>, headers: HeaderMap) -> AppResult<Response> {
+    if let Some(token) = session_token(&headers) {
+        delete_session(&state.pool, token).await?;
+    }
+    Ok((
+        [(
+            SET_COOKIE,
+            session_cookie("", Duration::zero(), state.settings.secure_cookies),
+        )],
+        Redirect::to("/login"),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn passwords_are_salted_and_verified() {
+        let hash = hash_password("correct horse").unwrap();
+        assert_ne!(hash, hash_password("correct horse").unwrap());
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("battery staple", &hash));
+        assert!(!verify_password("correct horse", "not a hash"));
+    }
+
+    #[test]
+    fn credentials_are_read_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_static("theme=dark; snowlog_session=abc123"),
+        );
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Basic YWxpY2U6czNjcjN0OnBhc3M="),
+        );
+        assert_eq!(Some("abc123"), session_token(&headers));
+        assert_eq!(
+            Some(("alice".to_owned(), "s3cr3t:pass".to_owned())),
+            basic_credentials(&headers)
+        );
+    }
+
+    #[test]
+    fn session_cookies_are_secure_unless_turned_off() {
+        let cookie = session_cookie("abc123", Duration::days(1), true);
+        assert_eq!(
+            "snowlog_session=abc123; Path=/; HttpOnly; SameSite=Lax; Max-Age=86400; Secure",
+            cookie
+        );
+        assert!(!session_cookie("abc123", Duration::days(1), false).contains("Secure"));
+    }
+}
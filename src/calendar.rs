@@ -0,0 +1,150 @@
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use serde::Serialize;
+
+use crate::models::Activity;
+
+#[derive(Debug, Serialize)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    /// False for the days of the months before and after that fill up the
+    /// first and last week.
+    pub in_month: bool,
+    pub is_today: bool,
+    pub activities: Vec<Activity>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct YearMonth {
+    pub year: i32,
+    pub month: u32,
+}
+
+impl From<NaiveDate> for YearMonth {
+    fn from(date: NaiveDate) -> Self {
+        YearMonth {
+            year: date.year(),
+            month: date.month(),
+        }
+    }
+}
+
+/// A month laid out as whole weeks starting on Monday.
+#[derive(Debug, Serialize)]
+pub struct CalendarMonth {
+    pub first: NaiveDate,
+    pub label: String,
+    pub previous: YearMonth,
+    pub next: YearMonth,
+    pub weeks: Vec<Vec<CalendarDay>>,
+}
+
+impl CalendarMonth {
+    /// The first day shown and the day after the last one.
+    pub fn range(year: i32, month: u32) -> Option<(NaiveDate, NaiveDate)> {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let start = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+        let last = first.checked_add_months(Months::new(1))? - Duration::days(1);
+        let end = last + Duration::days(7 - last.weekday().num_days_from_monday() as i64);
+        Some((start, end))
+    }
+
+    /// Puts every activity on its day. Activities outside the grid are left out.
+    pub fn new(
+        year: i32,
+        month: u32,
+        activities: Vec<Activity>,
+        today: NaiveDate,
+    ) -> Option<CalendarMonth> {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let (start, end) = Self::range(year, month)?;
+        let mut days: Vec<CalendarDay> = start
+            .iter_days()
+            .take_while(|date| *date < end)
+            .map(|date| CalendarDay {
+                date,
+                in_month: date.month() == month,
+                is_today: date == today,
+                activities: vec![],
+            })
+            .collect();
+
+        let mut activities = activities;
+        activities.sort_by_key(|x| x.date);
+        for activity in activities {
+            let index = (activity.date.date() - start).num_days();
+            if let Some(day) = usize::try_from(index).ok().and_then(|i| days.get_mut(i)) {
+                day.activities.push(activity);
+            }
+        }
+
+        let mut weeks = vec![];
+        while !days.is_empty() {
+            let rest = days.split_off(7.min(days.len()));
+            weeks.push(days);
+            days = rest;
+        }
+
+        Some(CalendarMonth {
+            first,
+            label: first.format("%B %Y").to_string(),
+            previous: first.checked_sub_months(Months::new(1))?.into(),
+            next: first.checked_add_months(Months::new(1))?.into(),
+            weeks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Timelike;
+
+    use super::*;
+
+    fn activity(date: &str) -> Activity {
+        Activity {
+            duration_hours: Some(2.0),
+            ..crate::models::tests::activity(date, "Skis")
+        }
+    }
+
+    #[test]
+    fn months_are_whole_weeks_from_monday() {
+        let today = NaiveDate::from_ymd_opt(2025, 2, 3).unwrap();
+        let activities = vec![
+            activity("2025-02-03T14:00:00"),
+            activity("2025-02-03T09:00:00"),
+            activity("2025-03-02T10:00:00"),
+            activity("2025-03-03T10:00:00"),
+        ];
+        let month = CalendarMonth::new(2025, 2, activities, today).unwrap();
+
+        // February 2025 starts on a Saturday and ends on a Friday.
+        assert_eq!(5, month.weeks.len());
+        let first_week = &month.weeks[0];
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2025, 1, 27).unwrap(),
+            first_week[0].date
+        );
+        assert!(!first_week[0].in_month);
+        assert!(first_week[5].in_month);
+
+        let monday = &month.weeks[1][0];
+        assert!(monday.is_today);
+        assert_eq!(2, monday.activities.len());
+        assert_eq!(9, monday.activities[0].date.hour());
+
+        let last = &month.weeks[4][6];
+        assert_eq!(NaiveDate::from_ymd_opt(2025, 3, 2).unwrap(), last.date);
+        assert_eq!(1, last.activities.len());
+
+        assert_eq!("February 2025", month.label);
+        assert_eq!(
+            YearMonth {
+                year: 2025,
+                month: 1
+            },
+            month.previous
+        );
+        assert!(CalendarMonth::new(2025, 13, vec![], today).is_none());
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{DailyTotal, ScoreCount, TypeDayTotal};
+use crate::season::Season;
+
+const WIDTH: f64 = 600.0;
+const HEIGHT: f64 = 200.0;
+/// Room for the axis labels to the left of and below the plot.
+const MARGIN_LEFT: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 20.0;
+const SCORE_BUCKETS: usize = 10;
+/// Size of a day in the heatmap, and the space between days.
+const HEATMAP_CELL: f64 = 12.0;
+const HEATMAP_STEP: f64 = 14.0;
+/// Shades from no activity to the most active days.
+const HEATMAP_COLORS: [&str; 5] = ["#2a2f35", "#12405c", "#1f6491", "#3b8ec5", "#7cc3ef"];
+
+fn escape(value: &str) -> String {
+    minijinja::HtmlEscape(value).to_string()
+}
+
+fn plot_width() -> f64 {
+    WIDTH - MARGIN_LEFT
+}
+
+fn plot_height() -> f64 {
+    HEIGHT - MARGIN_BOTTOM
+}
+
+fn y_for(value: f64, max: f64) -> f64 {
+    plot_height() - value / max * plot_height()
+}
+
+/// Starts an SVG document with a horizontal axis and the largest value
+/// written next to the top of the vertical axis.
+fn open_svg(title: &str, max_label: &str) -> String {
+    let mut svg = String::new();
+    write!(
+        svg,
+        r#"<svg class="chart" viewBox="0 0 {WIDTH} {HEIGHT}" role="img" xmlns="http://www.w3.org/2000/svg"><title>{}</title>"#,
+        escape(title)
+    )
+    .unwrap();
+    write!(
+        svg,
+        r#"<line class="axis" x1="{MARGIN_LEFT}" y1="{0}" x2="{WIDTH}" y2="{0}" />"#,
+        plot_height()
+    )
+    .unwrap();
+    write!(
+        svg,
+        r#"<text class="label" x="{}" y="12" text-anchor="end">{}</text>"#,
+        MARGIN_LEFT - 4.0,
+        escape(max_label)
+    )
+    .unwrap();
+    svg
+}
+
+fn x_label(svg: &mut String, x: f64, text: &str) {
+    write!(
+        svg,
+        r#"<text class="label" x="{x:.1}" y="{HEIGHT}" text-anchor="middle">{}</text>"#,
+        escape(text)
+    )
+    .unwrap();
+}
+
+/// Every type with its colour, in the order they are stacked.
+fn types<'a>(types: impl Iterator<Item = (&'a str, &'a str)>) -> BTreeMap<&'a str, String> {
+    types
+        .map(|(r#type, color)| (r#type, color.to_owned()))
+        .collect()
+}
+
+/// The first day of every month that overlaps the season.
+fn months(season: &Season) -> Vec<NaiveDate> {
+    let first = season.start.date().with_day(1).expect("Valid date");
+    (0..)
+        .map_while(|i| first.checked_add_months(Months::new(i)))
+        .take_while(|month| *month < season.end.date())
+        .collect()
+}
+
+/// One line per type showing the hours logged so far at each point in the
+/// season.
+pub fn cumulative_hours(totals: &[TypeDayTotal], season: &Season) -> String {
+    let season_days = (season.end - season.start).num_days() as f64;
+    let x_for = |date: NaiveDate| {
+        let days = (date - season.start.date()).num_days() as f64;
+        MARGIN_LEFT + days / season_days * plot_width()
+    };
+
+    let mut lines: BTreeMap<&str, Vec<(NaiveDate, f64)>> = BTreeMap::new();
+    let mut sorted: Vec<&TypeDayTotal> = totals.iter().collect();
+    sorted.sort_by_key(|x| x.date);
+    for day in sorted {
+        let line = lines.entry(&day.r#type).or_default();
+        let total = line.last().map(|(_, hours)| *hours).unwrap_or_default();
+        line.push((day.date, total + day.hours));
+    }
+    let colors = types(
+        totals
+            .iter()
+            .map(|x| (x.r#type.as_str(), x.type_color.as_str())),
+    );
+    let max = lines
+        .values()
+        .filter_map(|line| line.last())
+        .map(|(_, hours)| *hours)
+        .fold(0.0, f64::max)
+        .max(1.0);
+
+    let mut svg = open_svg("Cumulative hours", &format!("{:.0} h", max));
+    for month in months(season) {
+        x_label(
+            &mut svg,
+            x_for(month) + 12.0,
+            &month.format("%b").to_string(),
+        );
+    }
+    for (r#type, line) in &lines {
+        let mut points = format!("{:.1},{:.1}", MARGIN_LEFT, plot_height());
+        let mut previous = 0.0;
+        for (date, hours) in line {
+            let x = x_for(*date);
+            write!(
+                points,
+                " {x:.1},{:.1} {x:.1},{:.1}",
+                y_for(previous, max),
+                y_for(*hours, max)
+            )
+            .unwrap();
+            previous = *hours;
+        }
+        write!(
+            svg,
+            r#"<polyline points="{points}" fill="none" stroke="{}" stroke-width="2"><title>{}</title></polyline>"#,
+            colors[r#type],
+            escape(r#type)
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Draws one bar per bucket, with the count for each type stacked on top of
+/// each other.
+fn stacked_bars(
+    title: &str,
+    labels: &[String],
+    types: &BTreeMap<&str, String>,
+    counts: &BTreeMap<(usize, &str), usize>,
+) -> String {
+    let totals: Vec<usize> = (0..labels.len())
+        .map(|bucket| types.keys().filter_map(|t| counts.get(&(bucket, t))).sum())
+        .collect();
+    let max = totals.iter().copied().max().unwrap_or_default().max(1) as f64;
+    let slot = plot_width() / labels.len().max(1) as f64;
+    let bar_width = slot * 0.7;
+
+    let mut svg = open_svg(title, &format!("{:.0}", max));
+    for (bucket, label) in labels.iter().enumerate() {
+        let x = MARGIN_LEFT + slot * bucket as f64 + (slot - bar_width) / 2.0;
+        x_label(&mut svg, x + bar_width / 2.0, label);
+
+        let mut stacked = 0;
+        for (r#type, color) in types {
+            let Some(&count) = counts.get(&(bucket, r#type)) else {
+                continue;
+            };
+            let top = y_for((stacked + count) as f64, max);
+            let height = y_for(stacked as f64, max) - top;
+            write!(
+                svg,
+                r#"<rect x="{x:.1}" y="{top:.1}" width="{bar_width:.1}" height="{height:.1}" fill="{}"><title>{}: {}</title></rect>"#,
+                color,
+                escape(r#type),
+                count
+            )
+            .unwrap();
+            stacked += count;
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// The number of days with activities in every month of the season, stacked
+/// by type.
+pub fn days_per_month(totals: &[TypeDayTotal], season: &Season) -> String {
+    let months = months(season);
+    let types = types(
+        totals
+            .iter()
+            .map(|x| (x.r#type.as_str(), x.type_color.as_str())),
+    );
+    // There is one total per type and day, so each counts as a day.
+    let mut counts = BTreeMap::new();
+    for day in totals {
+        let first = day.date.with_day(1).expect("Valid date");
+        if let Some(bucket) = months.iter().rposition(|month| *month <= first) {
+            *counts.entry((bucket, day.r#type.as_str())).or_default() += 1;
+        }
+    }
+
+    let labels: Vec<String> = months.iter().map(|x| x.format("%b").to_string()).collect();
+    stacked_bars("Days per month", &labels, &types, &counts)
+}
+
+/// How the scores are spread between 0 and 1, stacked by type.
+pub fn score_distribution(scores: &[ScoreCount]) -> String {
+    let types = types(
+        scores
+            .iter()
+            .map(|x| (x.r#type.as_str(), x.type_color.as_str())),
+    );
+    let mut counts = BTreeMap::new();
+    for count in scores {
+        let bucket = ((count.score * SCORE_BUCKETS as f64) as usize).min(SCORE_BUCKETS - 1);
+        *counts.entry((bucket, count.r#type.as_str())).or_default() += count.activities as usize;
+    }
+
+    let labels: Vec<String> = (0..SCORE_BUCKETS)
+        .map(|bucket| format!("{:.1}", bucket as f64 / SCORE_BUCKETS as f64))
+        .collect();
+    stacked_bars("Score distribution", &labels, &types, &counts)
+}
+
+/// What the heatmap shades days by.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeatmapValue {
+    #[default]
+    Hours,
+    Score,
+}
+
+/// Which days the heatmap shows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeatmapRange {
+    /// From the start to the end of the season.
+    #[default]
+    Season,
+    /// The calendar year the season starts in.
+    Year,
+}
+
+impl HeatmapRange {
+    /// The first day shown, and the day after the last one.
+    pub fn dates(self, season: &Season) -> (NaiveDate, NaiveDate) {
+        match self {
+            HeatmapRange::Season => (season.start.date(), season.end.date()),
+            HeatmapRange::Year => (
+                NaiveDate::from_ymd_opt(season.year, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(season.year + 1, 1, 1).unwrap(),
+            ),
+        }
+    }
+}
+
+/// One square per day from `start` until `end`, in columns of weeks starting
+/// on Monday. Hours are shaded relative to the busiest day, while scores are
+/// shaded on their own scale from 0 to 1.
+pub fn heatmap(
+    days: &[DailyTotal],
+    start: NaiveDate,
+    end: NaiveDate,
+    value: HeatmapValue,
+) -> String {
+    let value_of = |day: &DailyTotal| match value {
+        HeatmapValue::Hours => day.hours,
+        HeatmapValue::Score => day.average_score.unwrap_or_default(),
+    };
+    let max = match value {
+        HeatmapValue::Hours => days.iter().map(value_of).fold(0.0, f64::max),
+        HeatmapValue::Score => 1.0,
+    };
+    let by_date: BTreeMap<NaiveDate, &DailyTotal> = days.iter().map(|x| (x.date, x)).collect();
+
+    let first_monday = start - Duration::days(start.weekday().num_days_from_monday() as i64);
+    let weeks = ((end - first_monday).num_days() as f64 / 7.0).ceil();
+    let width = MARGIN_LEFT + weeks * HEATMAP_STEP;
+    let height = MARGIN_BOTTOM + 7.0 * HEATMAP_STEP;
+
+    let mut svg = String::new();
+    write!(
+        svg,
+        r#"<svg class="chart heatmap" viewBox="0 0 {width} {height}" role="img" xmlns="http://www.w3.org/2000/svg"><title>Activity per day</title>"#,
+    )
+    .unwrap();
+    for (row, label) in [(0, "Mon"), (2, "Wed"), (4, "Fri")] {
+        write!(
+            svg,
+            r#"<text class="label" x="{}" y="{:.1}" text-anchor="end">{label}</text>"#,
+            MARGIN_LEFT - 4.0,
+            MARGIN_BOTTOM + row as f64 * HEATMAP_STEP + HEATMAP_CELL - 2.0,
+        )
+        .unwrap();
+    }
+
+    for date in start.iter_days().take_while(|date| *date < end) {
+        let week = (date - first_monday).num_days() / 7;
+        let x = MARGIN_LEFT + week as f64 * HEATMAP_STEP;
+        if date.day() == 1 {
+            write!(
+                svg,
+                r#"<text class="label" x="{x:.1}" y="{:.1}">{}</text>"#,
+                MARGIN_BOTTOM - 6.0,
+                date.format("%b")
+            )
+            .unwrap();
+        }
+
+        let y = MARGIN_BOTTOM + date.weekday().num_days_from_monday() as f64 * HEATMAP_STEP;
+        let day = by_date.get(&date);
+        let amount = day.map(|x| value_of(x)).unwrap_or_default();
+        let level = if day.is_none() || max <= 0.0 {
+            0
+        } else {
+            // Any activity at all gets at least the lightest shade.
+            ((amount / max * 4.0).ceil() as usize).clamp(1, 4)
+        };
+        let title = match (day, value) {
+            (None, _) => format!("{}: nothing", date),
+            (Some(x), HeatmapValue::Hours) => format!("{}: {:.2} hours", date, x.hours),
+            (Some(x), HeatmapValue::Score) => match x.average_score {
+                Some(score) => format!("{}: score {:.2}", date, score),
+                None => format!("{}: no score", date),
+            },
+        };
+        write!(
+            svg,
+            r#"<rect x="{x:.1}" y="{y:.1}" width="{HEATMAP_CELL}" height="{HEATMAP_CELL}" rx="2" fill="{}"><title>{}</title></rect>"#,
+            HEATMAP_COLORS[level],
+            escape(&title)
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::string_to_rgb;
+    use crate::season::SeasonStart;
+
+    fn total(date: &str, r#type: &str, activities: i64, hours: f64) -> TypeDayTotal {
+        TypeDayTotal {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            r#type: r#type.into(),
+            type_color: string_to_rgb(r#type),
+            activities,
+            hours,
+        }
+    }
+
+    fn score(r#type: &str, score: f64) -> ScoreCount {
+        ScoreCount {
+            r#type: r#type.into(),
+            type_color: string_to_rgb(r#type),
+            score,
+            activities: 1,
+        }
+    }
+
+    #[test]
+    fn charts_are_coloured_by_type() {
+        let season = Season::new(2024, SeasonStart { month: 10, day: 15 }).unwrap();
+        assert_eq!(13, months(&season).len());
+
+        let totals = [
+            total("2024-12-01", "Skis", 2, 4.0),
+            total("2025-01-01", "<Board>", 1, 2.0),
+        ];
+        let scores = [score("Skis", 1.0), score("Skis", 0.25)];
+        let color = string_to_rgb("Skis");
+
+        let days = days_per_month(&totals, &season);
+        assert_eq!(1, days.matches(&format!("fill=\"{}\"", color)).count());
+        assert!(days.contains("&lt;Board&gt;: 1"));
+        assert!(days.contains("Skis: 1"));
+
+        let scores = score_distribution(&scores);
+        assert_eq!(2, scores.matches(&format!("fill=\"{}\"", color)).count());
+
+        let hours = cumulative_hours(&totals, &season);
+        assert_eq!(2, hours.matches("<polyline").count());
+        assert!(hours.contains(">4 h<"));
+    }
+
+    #[test]
+    fn heatmap_shades_days_relative_to_the_busiest() {
+        let day = |date: &str, hours: f64, score: Option<f64>| DailyTotal {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            activities: 1,
+            hours,
+            average_score: score,
+        };
+        let days = [
+            day("2025-01-01", 4.0, Some(0.2)),
+            day("2025-01-02", 0.5, Some(1.0)),
+        ];
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+
+        let hours = heatmap(&days, start, end, HeatmapValue::Hours);
+        assert_eq!(365, hours.matches("<rect").count());
+        assert!(hours.contains(">Jan<") && hours.contains(">Dec<"));
+        assert!(hours.contains(&format!(
+            r#"fill="{}"><title>2025-01-01: 4.00 hours"#,
+            HEATMAP_COLORS[4]
+        )));
+        assert!(hours.contains(&format!(
+            r#"fill="{}"><title>2025-01-02: 0.50 hours"#,
+            HEATMAP_COLORS[1]
+        )));
+        assert!(hours.contains(&format!(
+            r#"fill="{}"><title>2025-01-03: nothing"#,
+            HEATMAP_COLORS[0]
+        )));
+
+        let scores = heatmap(&days, start, end, HeatmapValue::Score);
+        assert!(scores.contains(&format!(
+            r#"fill="{}"><title>2025-01-02: score 1.00"#,
+            HEATMAP_COLORS[4]
+        )));
+    }
+
+    #[test]
+    fn heatmap_ranges_cover_the_season_or_its_year() {
+        let season = Season::new(2024, SeasonStart { month: 10, day: 15 }).unwrap();
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(
+            (date(2024, 10, 15), date(2025, 10, 15)),
+            HeatmapRange::Season.dates(&season)
+        );
+        assert_eq!(
+            (date(2024, 1, 1), date(2025, 1, 1)),
+            HeatmapRange::Year.dates(&season)
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use sqlx::SqlitePool;
+
+use crate::import_export::{preview_import, run_import, write_csv};
+use crate::models::{
+    get_activities_from, get_all_types, get_summary, get_user_credentials, get_users,
+    insert_activity, ActivityFilter, User,
+};
+use crate::season::Season;
+use crate::settings::{Settings, SettingsArgs};
+use crate::validation::ActivityForm;
+
+/// Keep a log of ski days from the browser or the terminal.
+#[derive(Debug, Parser)]
+#[command(name = "log")]
+pub struct Cli {
+    /// The account to use. Can be left out when there is only one.
+    #[arg(long, global = true, env = "SNOWLOG_USER")]
+    pub user: Option<String>,
+
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+
+    /// Print the settings that would be used, in the format of the settings
+    /// file, and exit.
+    #[arg(long)]
+    pub print_config: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the web server. This is the default.
+    Serve,
+    /// Add an activity.
+    Add {
+        /// Defaults to now.
+        #[arg(long)]
+        date: Option<String>,
+        /// Defaults to the first type on the types page.
+        #[arg(long = "type")]
+        r#type: Option<String>,
+        #[arg(long, default_value = "")]
+        location: String,
+        /// Duration in hours.
+        #[arg(long, default_value = "")]
+        duration: String,
+        /// Between 0 and 1.
+        #[arg(long, default_value = "")]
+        score: String,
+        #[arg(long, default_value = "")]
+        description: String,
+    },
+    /// List the activities in a season.
+    List {
+        /// The year the season starts in. Defaults to the current season.
+        #[arg(long, conflicts_with = "all")]
+        season: Option<i32>,
+        /// List every activity.
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show days and hours per type for a season.
+    Summary {
+        /// The year the season starts in. Defaults to the current season.
+        #[arg(long)]
+        season: Option<i32>,
+    },
+    /// Import activities from a CSV file like the one from `export`.
+    Import {
+        file: PathBuf,
+        /// Only show what would be imported.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write every activity as CSV to a file, or to stdout.
+    Export { file: Option<PathBuf> },
+    /// Run the database migrations and exit.
+    Migrate,
+}
+
+/// Finds the account the command is for.
+pub async fn find_user(pool: &SqlitePool, username: Option<&str>) -> Result<User> {
+    if let Some(username) = username {
+        let user = get_user_credentials(pool, username)
+            .await?
+            .with_context(|| format!("There is no user named '{}'", username))?;
+        return Ok(User {
+            id: user.id,
+            username: user.username,
+        });
+    }
+
+    let mut users = get_users(pool).await?;
+    match users.len() {
+        0 => bail!("There are no users yet. Register one in the browser first"),
+        1 => Ok(users.remove(0)),
+        _ => {
+            let names: Vec<String> = users.into_iter().map(|x| x.username).collect();
+            bail!("Choose one of the users with --user: {}", names.join(", "))
+        }
+    }
+}
+
+fn season(settings: &Settings, year: Option<i32>) -> Result<Season> {
+    match year {
+        Some(year) => Season::new(year, settings.season_start)
+            .with_context(|| format!("There is no season {}", year)),
+        None => Ok(settings.current_season()),
+    }
+}
+
+/// Runs every command except `serve` and `migrate`, which need more than the
+/// database.
+pub async fn run(
+    command: Command,
+    pool: &SqlitePool,
+    user: &User,
+    settings: &Settings,
+    out: &mut impl Write,
+) -> Result<()> {
+    let now = settings.local_now();
+    match command {
+        Command::Serve | Command::Migrate => {}
+        Command::Add {
+            date,
+            r#type,
+            location,
+            duration,
+            score,
+            description,
+        } => {
+            let r#type = match r#type {
+                Some(x) => x,
+                None => get_all_types(pool, user.id)
+                    .await?
+                    .into_iter()
+                    .next()
+                    .context("There are no types yet, pass one with --type")?,
+            };
+            let form = ActivityForm {
+                id: None,
+                date: date.unwrap_or_else(|| now.format("%Y-%m-%dT%H:%M:%S").to_string()),
+                location,
+                duration_hours: duration,
+                r#type,
+                score,
+                description,
+                track_id: None,
+            };
+            let activity = form.parse(now).map_err(|e| anyhow::anyhow!("{}", e))?;
+            let id = insert_activity(pool, user.id, activity).await?;
+            writeln!(out, "Added activity {}", id)?;
+        }
+        Command::List { season: year, all } => {
+            let (from, to) = if all {
+                (None, None)
+            } else {
+                let season = season(settings, year)?;
+                (Some(season.start), Some(season.end))
+            };
+            for activity in
+                get_activities_from(pool, user.id, from, to, &ActivityFilter::default(), None)
+                    .await?
+            {
+                writeln!(
+                    out,
+                    "{:>5}  {}  {:<12}  {:<16}  {:>6}  {:>4}",
+                    activity.id.unwrap_or_default(),
+                    activity.date.format("%Y-%m-%d %H:%M"),
+                    activity.r#type,
+                    activity.location,
+                    activity
+                        .duration_hours
+                        .map(|x| format!("{:.2} h", x))
+                        .unwrap_or_default(),
+                    activity
+                        .score
+                        .map(|x| format!("{:.2}", x))
+                        .unwrap_or_default(),
+                )?;
+            }
+        }
+        Command::Summary { season: year } => {
+            let season = season(settings, year)?;
+            writeln!(out, "Season {}", season.label())?;
+            let summaries = get_summary(
+                pool,
+                user.id,
+                Some(season.start),
+                Some(season.end),
+                &ActivityFilter::default(),
+            )
+            .await?;
+            for summary in &summaries {
+                writeln!(
+                    out,
+                    "{:<12}  {:>4} days  {:>7.2} hours",
+                    summary.r#type, summary.days, summary.hours
+                )?;
+            }
+            let days: i64 = summaries.iter().map(|x| x.days).sum();
+            let hours: f64 = summaries.iter().map(|x| x.hours).sum();
+            writeln!(
+                out,
+                "{:<12}  {:>4} days  {:>7.2} hours",
+                "Total", days, hours
+            )?;
+        }
+        Command::Import { file, dry_run } => {
+            let data =
+                std::fs::read(&file).with_context(|| format!("Failed to read {:?}", file))?;
+            let preview = if dry_run {
+                preview_import(pool, user.id, &data, now).await?
+            } else {
+                run_import(pool, user.id, &data, now).await?
+            };
+            for row in &preview.rows {
+                if let Some(reason) = &row.reason {
+                    writeln!(out, "Line {}: {}", row.line, reason)?;
+                }
+            }
+            writeln!(
+                out,
+                "{} {}, {} duplicates, {} rejected",
+                preview.created,
+                if dry_run { "to import" } else { "imported" },
+                preview.duplicates,
+                preview.rejected
+            )?;
+        }
+        Command::Export { file } => {
+            let activities =
+                get_activities_from(pool, user.id, None, None, &ActivityFilter::default(), None)
+                    .await?;
+            match file {
+                Some(file) => {
+                    let writer = std::fs::File::create(&file)
+                        .with_context(|| format!("Failed to create {:?}", file))?;
+                    write_csv(&activities, writer)?;
+                }
+                None => write_csv(&activities, out)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::models::create_user;
+
+    async fn setup() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        pool
+    }
+
+    fn parse(args: &[&str]) -> Command {
+        Cli::try_parse_from(args).unwrap().command.unwrap()
+    }
+
+    #[tokio::test]
+    async fn add_and_summarize() {
+        let pool = setup().await;
+        assert!(find_user(&pool, None).await.is_err());
+        create_user(&pool, "alice", "hash", NaiveDateTime::default())
+            .await
+            .unwrap();
+        let user = find_user(&pool, None).await.unwrap();
+        let settings = Settings::default();
+
+        let mut out = vec![];
+        let add = parse(&[
+            "log",
+            "add",
+            "--date",
+            "2025-01-05 10:00",
+            "--type",
+            "Skis",
+            "--duration",
+            "3,5",
+        ]);
+        run(add, &pool, &user, &settings, &mut out).await.unwrap();
+        let invalid = parse(&["log", "add", "--type", "Skis", "--score", "2"]);
+        assert!(run(invalid, &pool, &user, &settings, &mut out)
+            .await
+            .is_err());
+
+        let summary = parse(&["log", "summary", "--season", "2024"]);
+        run(summary, &pool, &user, &settings, &mut out)
+            .await
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("Added activity 1\nSeason 2024/25\n"));
+        assert!(out.contains("Skis             1 days     3.50 hours"));
+    }
+}
@@ -0,0 +1,143 @@
+use axum::extract::multipart::MultipartError;
+use axum::extract::rejection::{FormRejection, JsonRejection};
+use axum::extract::State;
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
+use minijinja::context;
+use tracing::error;
+
+use crate::validation::FieldErrors;
+use crate::AppState;
+
+/// Errors returned by request handlers.
+#[derive(Debug)]
+pub enum AppError {
+    Unauthorized,
+    NotFound(String),
+    BadRequest(String),
+    Validation(String),
+    Database(sqlx::Error),
+    Internal(anyhow::Error),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Message that is safe to show to the user. Database and internal errors
+    /// are only logged.
+    pub fn message(&self) -> String {
+        match self {
+            AppError::Unauthorized => "You need to log in".to_owned(),
+            AppError::NotFound(msg) | AppError::BadRequest(msg) | AppError::Validation(msg) => {
+                msg.clone()
+            }
+            AppError::Database(_) | AppError::Internal(_) => "Something went wrong".to_owned(),
+        }
+    }
+
+    pub fn log(&self) {
+        match self {
+            AppError::Database(e) => error!("Database error: {:#}", e),
+            AppError::Internal(e) => error!("Internal error: {:#}", e),
+            _ => {}
+        }
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(value: anyhow::Error) -> Self {
+        match value.downcast::<sqlx::Error>() {
+            Ok(e) => AppError::Database(e),
+            Err(e) => AppError::Internal(e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(value: sqlx::Error) -> Self {
+        AppError::Database(value)
+    }
+}
+
+impl From<FieldErrors> for AppError {
+    fn from(value: FieldErrors) -> Self {
+        AppError::Validation(value.to_string())
+    }
+}
+
+impl From<FormRejection> for AppError {
+    fn from(value: FormRejection) -> Self {
+        match value {
+            FormRejection::FailedToDeserializeForm(e) => AppError::Validation(e.body_text()),
+            other => AppError::BadRequest(other.body_text()),
+        }
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(value: JsonRejection) -> Self {
+        match value {
+            JsonRejection::JsonDataError(e) => AppError::Validation(e.body_text()),
+            other => AppError::BadRequest(other.body_text()),
+        }
+    }
+}
+
+impl From<MultipartError> for AppError {
+    fn from(value: MultipartError) -> Self {
+        AppError::BadRequest(value.body_text())
+    }
+}
+
+/// Marker attached to error responses so [`render_error_pages`] can replace the
+/// plain text body with the error template.
+#[derive(Clone)]
+struct ErrorPage {
+    message: String,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        if let AppError::Unauthorized = self {
+            return Redirect::to("/login").into_response();
+        }
+        self.log();
+        let status = self.status();
+        let message = self.message();
+        let mut response = (status, message.clone()).into_response();
+        response.extensions_mut().insert(ErrorPage { message });
+        response
+    }
+}
+
+pub async fn render_error_pages<B>(
+    State(state): State<AppState>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let response = next.run(req).await;
+    let Some(page) = response.extensions().get::<ErrorPage>().cloned() else {
+        return response;
+    };
+    let status = response.status();
+    let html = state.render(
+        "error.html",
+        context!(
+            status => status.as_u16(),
+            reason => status.canonical_reason().unwrap_or("Error"),
+            message => page.message,
+        ),
+    );
+    (status, html).into_response()
+}
@@ -0,0 +1,144 @@
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+
+use crate::models::{Activity, ActivityRevision};
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DiffLine {
+    /// One of "added", "removed" or "same".
+    pub kind: &'static str,
+    pub text: String,
+}
+
+/// Line by line difference between two descriptions.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            kind: match change.tag() {
+                ChangeTag::Insert => "added",
+                ChangeTag::Delete => "removed",
+                ChangeTag::Equal => "same",
+            },
+            text: change.value().trim_end_matches('\n').to_owned(),
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Change {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+fn fields(activity: &Activity) -> [(&'static str, String); 5] {
+    let float = |value: Option<f64>| value.map(|x| format!("{:.2}", x)).unwrap_or_default();
+    [
+        ("Date", activity.date.format("%Y-%m-%d %H:%M").to_string()),
+        ("Type", activity.r#type.clone()),
+        ("Location", activity.location.clone()),
+        ("Duration", float(activity.duration_hours)),
+        ("Score", float(activity.score)),
+    ]
+}
+
+/// The fields other than the description that differ between two versions.
+pub fn changes(old: &Activity, new: &Activity) -> Vec<Change> {
+    fields(old)
+        .into_iter()
+        .zip(fields(new))
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((field, old), (_, new))| Change { field, old, new })
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevisionView<'a> {
+    pub revision: &'a ActivityRevision,
+    pub changes: Vec<Change>,
+    /// Empty when the description wasn't changed.
+    pub description: Vec<DiffLine>,
+}
+
+/// Describes what each edit changed, by comparing every revision with the
+/// version that replaced it. Revisions are expected newest first.
+pub fn describe_revisions<'a>(
+    current: &Activity,
+    revisions: &'a [ActivityRevision],
+) -> Vec<RevisionView<'a>> {
+    let newer = std::iter::once(current).chain(revisions.iter().map(|x| &x.activity));
+    revisions
+        .iter()
+        .zip(newer)
+        .map(|(revision, newer)| {
+            let old = &revision.activity;
+            RevisionView {
+                revision,
+                changes: changes(old, newer),
+                description: if old.description == newer.description {
+                    vec![]
+                } else {
+                    diff_lines(&old.description, &newer.description)
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    fn activity(location: &str, description: &str) -> Activity {
+        Activity {
+            id: Some(1),
+            location: location.into(),
+            duration_hours: Some(2.0),
+            description: description.into(),
+            ..crate::models::tests::activity("2025-01-05T10:00:00", "Skis")
+        }
+    }
+
+    #[test]
+    fn revisions_are_compared_with_the_next_version() {
+        let edited_at = NaiveDateTime::from_str("2025-01-06T10:00:00").unwrap();
+        let revision = |id, activity| ActivityRevision {
+            id,
+            edited_at,
+            edited_by: None,
+            activity,
+        };
+        let current = activity("Tryvann", "Powder\nCold");
+        let revisions = [
+            revision(2, activity("Hemsedal", "Powder\nCold")),
+            revision(1, activity("Hemsedal", "Powder\nWindy")),
+        ];
+
+        let views = describe_revisions(&current, &revisions);
+        assert_eq!(
+            vec![Change {
+                field: "Location",
+                old: "Hemsedal".into(),
+                new: "Tryvann".into()
+            }],
+            views[0].changes
+        );
+        assert!(views[0].description.is_empty());
+
+        assert!(views[1].changes.is_empty());
+        let diff: Vec<_> = views[1]
+            .description
+            .iter()
+            .map(|x| (x.kind, x.text.as_str()))
+            .collect();
+        assert_eq!(
+            vec![("same", "Powder"), ("removed", "Windy"), ("added", "Cold")],
+            diff
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use chrono::{Duration, LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::models::Activity;
+
+/// Used for the end of activities without a duration.
+const DEFAULT_DURATION_HOURS: f64 = 1.0;
+
+/// Lines longer than this many bytes are folded, see RFC 5545 section 3.1.
+const MAX_LINE_LENGTH: usize = 75;
+
+/// The text of a Markdown document without the markup. Blocks are separated by
+/// blank lines, and list items are put on lines of their own.
+pub fn markdown_to_text(markdown: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(value) | Event::Code(value) => text.push_str(&value),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Start(Tag::Item) => text.push_str("- "),
+            Event::End(Tag::Item) => text.push('\n'),
+            Event::End(Tag::Paragraph | Tag::Heading(..) | Tag::CodeBlock(_)) => {
+                text.push_str("\n\n")
+            }
+            Event::End(Tag::List(_)) => text.push('\n'),
+            _ => {}
+        }
+    }
+    text.trim_end().to_owned()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Appends a content line, folded so that no line is longer than
+/// [`MAX_LINE_LENGTH`] bytes. Lines are never split inside a character.
+fn push_line(output: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            output.push_str("\r\n ");
+            length = 1;
+        }
+        output.push(c);
+        length += c.len_utf8();
+    }
+    output.push_str("\r\n");
+}
+
+/// Formats a UTC time, with the `Z` suffix.
+fn format_time(time: NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Converts a local time in `timezone` to UTC. Times that are skipped when the
+/// clocks go forward get the offset from after the change, and times that
+/// happen twice when they go back are taken as the first one.
+fn to_utc(time: NaiveDateTime, timezone: Tz) -> NaiveDateTime {
+    match timezone.from_local_datetime(&time) {
+        LocalResult::Single(x) | LocalResult::Ambiguous(x, _) => x.naive_utc(),
+        LocalResult::None => {
+            // With the offset from a day before, the skipped time lands just
+            // after the change, where the offset from after it can be read.
+            let before = timezone
+                .offset_from_utc_datetime(&(time - Duration::days(1)))
+                .fix();
+            let after = timezone.offset_from_utc_datetime(&(time - before)).fix();
+            time - after
+        }
+    }
+}
+
+/// The end of an activity, from its start and duration.
+fn activity_end(activity: &Activity) -> NaiveDateTime {
+    let hours = activity.duration_hours.unwrap_or(DEFAULT_DURATION_HOURS);
+    activity.date + Duration::seconds((hours * 3600.0).round() as i64)
+}
+
+/// Writes the activities as an iCalendar document with one event each.
+///
+/// Activity dates are local times in `timezone`, and are written in UTC so
+/// that calendar apps in other time zones show them at the right time. `now`
+/// is the UTC time used for the time stamp of the events.
+pub fn write_calendar(
+    activities: &[Activity],
+    name: &str,
+    timezone: Tz,
+    now: NaiveDateTime,
+) -> String {
+    let mut output = String::new();
+    let mut line = |line: String| push_line(&mut output, &line);
+    line("BEGIN:VCALENDAR".into());
+    line("VERSION:2.0".into());
+    line("PRODID:-//snowlog//snowlog//EN".into());
+    line("CALSCALE:GREGORIAN".into());
+    line(format!("X-WR-CALNAME:{}", escape(name)));
+    for activity in activities {
+        line("BEGIN:VEVENT".into());
+        if let Some(id) = activity.id {
+            line(format!("UID:activity-{}@snowlog", id));
+        }
+        line(format!("DTSTAMP:{}", format_time(now)));
+        line(format!(
+            "DTSTART:{}",
+            format_time(to_utc(activity.date, timezone))
+        ));
+        line(format!(
+            "DTEND:{}",
+            format_time(to_utc(activity_end(activity), timezone))
+        ));
+        line(format!("SUMMARY:{}", escape(&activity.r#type)));
+        if !activity.location.is_empty() {
+            line(format!("LOCATION:{}", escape(&activity.location)));
+        }
+        let description = markdown_to_text(&activity.description);
+        if !description.is_empty() {
+            line(format!("DESCRIPTION:{}", escape(&description)));
+        }
+        line("END:VEVENT".into());
+    }
+    line("END:VCALENDAR".into());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn activity(id: i64, duration_hours: Option<f64>, description: &str) -> Activity {
+        Activity {
+            id: Some(id),
+            location: "Tryvann, Oslo".into(),
+            duration_hours,
+            description: description.into(),
+            ..crate::models::tests::activity("2025-01-05T10:00:00", "Skis")
+        }
+    }
+
+    #[test]
+    fn activities_are_written_as_events() {
+        let now = NaiveDateTime::from_str("2025-01-06T08:00:00").unwrap();
+        let activities = [
+            activity(1, Some(2.5), "**Powder** day\n\n- Cold\n- Windy"),
+            activity(2, None, &"long ".repeat(40)),
+        ];
+        let calendar = write_calendar(&activities, "Snowlog", chrono_tz::Europe::Oslo, now);
+
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+        assert!(calendar.contains("UID:activity-1@snowlog\r\n"));
+        assert!(calendar.contains("DTSTAMP:20250106T080000Z\r\n"));
+        assert!(calendar.contains("DTSTART:20250105T090000Z\r\nDTEND:20250105T113000Z\r\n"));
+        assert!(calendar.contains("DTEND:20250105T100000Z\r\n"));
+        assert!(calendar.contains("SUMMARY:Skis\r\n"));
+        assert!(calendar.contains("LOCATION:Tryvann\\, Oslo\r\n"));
+        assert!(calendar.contains("DESCRIPTION:Powder day\\n\\n- Cold\\n- Windy\r\n"));
+        assert!(calendar
+            .split("\r\n")
+            .all(|line| line.len() <= MAX_LINE_LENGTH));
+
+        let unfolded = calendar.replace("\r\n ", "");
+        assert!(unfolded.contains(&format!("DESCRIPTION:{}\r\n", "long ".repeat(40).trim())));
+    }
+
+    #[test]
+    fn local_times_are_converted_to_utc() {
+        let utc = |time: &str, timezone: Tz| {
+            to_utc(NaiveDateTime::from_str(time).unwrap(), timezone).to_string()
+        };
+        let oslo = chrono_tz::Europe::Oslo;
+        assert_eq!("2025-07-01 08:00:00", utc("2025-07-01T10:00:00", oslo));
+        // Skipped when the clocks went forward, and repeated when they went back.
+        assert_eq!("2025-03-30 00:30:00", utc("2025-03-30T02:30:00", oslo));
+        assert_eq!("2025-10-26 00:30:00", utc("2025-10-26T02:30:00", oslo));
+
+        let new_york = chrono_tz::America::New_York;
+        assert_eq!("2025-07-01 14:00:00", utc("2025-07-01T10:00:00", new_york));
+        assert_eq!("2025-03-09 06:30:00", utc("2025-03-09T02:30:00", new_york));
+        assert_eq!("2025-11-02 05:30:00", utc("2025-11-02T01:30:00", new_york));
+    }
+}
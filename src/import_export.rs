@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::models::{get_activities_from, insert_activities, Activity, ActivityFilter};
+use crate::validation::ActivityForm;
+
+/// Column order used when exporting. Imports match columns by header name, so
+/// the order of the columns in an imported file doesn't matter.
+const COLUMNS: [&str; 6] = [
+    "date",
+    "location",
+    "duration_hours",
+    "type",
+    "score",
+    "description",
+];
+const REQUIRED_COLUMNS: [&str; 3] = ["date", "duration_hours", "type"];
+
+pub fn write_csv<W: Write>(activities: &[Activity], writer: W) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(COLUMNS)?;
+    for activity in activities {
+        writer.write_record([
+            activity.date.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            activity.location.clone(),
+            activity
+                .duration_hours
+                .map(|x| x.to_string())
+                .unwrap_or_default(),
+            activity.r#type.clone(),
+            activity.score.map(|x| x.to_string()).unwrap_or_default(),
+            activity.description.clone(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Create,
+    Duplicate,
+    Rejected,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportRow {
+    /// Line number in the file, counting the header as line 1.
+    pub line: usize,
+    pub form: ActivityForm,
+    pub status: ImportStatus,
+    pub reason: Option<String>,
+    #[serde(skip)]
+    activity: Option<Activity>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportPreview {
+    pub rows: Vec<ImportRow>,
+    pub created: usize,
+    pub duplicates: usize,
+    pub rejected: usize,
+}
+
+type DuplicateKey = (NaiveDateTime, String, String);
+
+fn duplicate_key(activity: &Activity) -> DuplicateKey {
+    (
+        activity.date,
+        activity.r#type.to_lowercase(),
+        activity.location.to_lowercase(),
+    )
+}
+
+/// Reads CSV rows into forms, matching columns by their header names.
+fn read_forms<R: Read>(reader: R) -> Result<Vec<(usize, ActivityForm)>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::Headers)
+        .from_reader(reader);
+    let headers: Vec<String> = reader
+        .headers()
+        .context("Failed to read the header row")?
+        .iter()
+        .map(|x| x.to_lowercase())
+        .collect();
+    let missing: Vec<&str> = REQUIRED_COLUMNS
+        .into_iter()
+        .filter(|column| !headers.iter().any(|h| h == column))
+        .collect();
+    if !missing.is_empty() {
+        bail!("Missing required columns: {}", missing.join(", "));
+    }
+
+    let mut forms = vec![];
+    for (index, record) in reader.records().enumerate() {
+        let line = index + 2;
+        let record = record.with_context(|| format!("Failed to read line {}", line))?;
+        let mut form = ActivityForm::default();
+        for (header, value) in headers.iter().zip(record.iter()) {
+            let value = value.to_owned();
+            match header.as_str() {
+                "date" => form.date = value,
+                "location" => form.location = value,
+                "duration_hours" => form.duration_hours = value,
+                "type" => form.r#type = value,
+                "score" => form.score = value,
+                "description" => form.description = value,
+                _ => {}
+            }
+        }
+        forms.push((line, form));
+    }
+    Ok(forms)
+}
+
+/// Works out what importing `data` would do without changing the database.
+pub async fn preview_import(
+    con: &SqlitePool,
+    owner_id: i64,
+    data: &[u8],
+    now: NaiveDateTime,
+) -> Result<ImportPreview> {
+    let existing =
+        get_activities_from(con, owner_id, None, None, &ActivityFilter::default(), None).await?;
+    let mut seen: HashSet<DuplicateKey> = existing.iter().map(duplicate_key).collect();
+
+    let mut preview = ImportPreview::default();
+    for (line, form) in read_forms(data)? {
+        let (status, reason, activity) = match form.parse(now) {
+            Err(errors) => (ImportStatus::Rejected, Some(errors.to_string()), None),
+            Ok(activity) => {
+                if seen.insert(duplicate_key(&activity)) {
+                    (ImportStatus::Create, None, Some(activity))
+                } else {
+                    let reason = "An activity with the same date, type and location exists";
+                    (ImportStatus::Duplicate, Some(reason.to_owned()), None)
+                }
+            }
+        };
+        match status {
+            ImportStatus::Create => preview.created += 1,
+            ImportStatus::Duplicate => preview.duplicates += 1,
+            ImportStatus::Rejected => preview.rejected += 1,
+        }
+        preview.rows.push(ImportRow {
+            line,
+            form,
+            status,
+            reason,
+            activity,
+        });
+    }
+    Ok(preview)
+}
+
+/// Inserts every row of `data` that the preview marks as [`ImportStatus::Create`].
+/// Nothing is imported if one of the rows fails.
+pub async fn run_import(
+    con: &SqlitePool,
+    owner_id: i64,
+    data: &[u8],
+    now: NaiveDateTime,
+) -> Result<ImportPreview> {
+    let mut preview = preview_import(con, owner_id, data, now).await?;
+    let activities = preview
+        .rows
+        .iter_mut()
+        .filter_map(|row| row.activity.take())
+        .collect();
+    insert_activities(con, owner_id, activities).await?;
+    Ok(preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::models::{create_user, get_all_locations};
+
+    async fn setup() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        pool
+    }
+
+    async fn add_user(pool: &SqlitePool, username: &str) -> i64 {
+        create_user(pool, username, "hash", NaiveDateTime::default())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn import_classifies_rows_and_round_trips() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        let now = NaiveDateTime::from_str("2025-06-01T00:00:00").unwrap();
+        let data = "\
+Type,Date,Duration_Hours,Location,Score,Description,Ignored
+Skis,2025-01-05T10:00:00,3.5,Hemsedal,0.8,\"Deep, light snow\",x
+Skis,2025-01-05T10:00:00,3.5,hemsedal,,Same day twice,x
+Snowboarding,not a date,2,Tryvann,,,x
+Snowboarding,2025-01-06,2,Tryvann,,,x
+Skis,1965-02-01T09:00:00,1,Finse,,Before 1970,x
+";
+        let preview = preview_import(&pool, owner, data.as_bytes(), now)
+            .await
+            .unwrap();
+        assert_eq!(
+            (3, 1, 1),
+            (preview.created, preview.duplicates, preview.rejected)
+        );
+        assert_eq!(ImportStatus::Duplicate, preview.rows[1].status);
+        assert_eq!(4, preview.rows[2].line);
+        assert!(
+            get_activities_from(&pool, owner, None, None, &ActivityFilter::default(), None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        run_import(&pool, owner, data.as_bytes(), now)
+            .await
+            .unwrap();
+        let activities =
+            get_activities_from(&pool, owner, None, None, &ActivityFilter::default(), None)
+                .await
+                .unwrap();
+        assert_eq!(3, activities.len());
+
+        let mut exported = vec![];
+        write_csv(&activities, &mut exported).unwrap();
+        let again = preview_import(&pool, owner, &exported, now).await.unwrap();
+        assert_eq!((0, 3), (again.created, again.duplicates));
+    }
+
+    #[tokio::test]
+    async fn failed_imports_add_nothing() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        sqlx::query(
+            "create trigger fail before insert on activities when new.location = 'Broken'
+            begin select raise(abort, 'Broken'); end",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let data = "\
+type,date,duration_hours,location
+Skis,2025-01-05T10:00:00,3.5,Hemsedal
+Skis,2025-01-06T10:00:00,2,Broken
+";
+        let now = NaiveDateTime::from_str("2025-06-01T00:00:00").unwrap();
+        assert!(run_import(&pool, owner, data.as_bytes(), now)
+            .await
+            .is_err());
+
+        let activities =
+            get_activities_from(&pool, owner, None, None, &ActivityFilter::default(), None)
+                .await
+                .unwrap();
+        assert!(activities.is_empty());
+        assert!(get_all_locations(&pool, owner).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_columns_are_reported() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        let error = preview_import(&pool, owner, b"date,location\n", NaiveDateTime::default())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            "Missing required columns: duration_hours, type",
+            error.to_string()
+        );
+    }
+}
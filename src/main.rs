@@ -1,19 +1,40 @@
+mod api;
+mod assets;
+mod auth;
+mod calendar;
+mod charts;
+mod cli;
+mod error;
+mod history;
+mod ical;
+mod import_export;
 mod models;
+mod photos;
 mod routes;
+mod season;
+mod settings;
+mod stats;
 mod templates;
+mod tracks;
+mod validation;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use assets::AssetSource;
 use axum::{
+    extract::DefaultBodyLimit,
     http::{Request, StatusCode},
     middleware::{self, Next},
     response::{Html, Response},
     routing::{get, post},
     Router,
 };
-use regex::Regex;
+use clap::Parser;
+use cli::{Cli, Command};
+use photos::PhotoStore;
 use serde::Serialize;
+use settings::{sqlite_path, Settings};
 use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
-use std::{fs::File, net::SocketAddr, path::Path, time::Instant};
+use std::{fs::File, sync::Arc, time::Instant};
 use templates::CachedEnvironment;
 use tokio::signal::unix::SignalKind;
 use tower_http::services::ServeDir;
@@ -23,6 +44,8 @@ use tracing::info;
 pub struct AppState {
     pool: SqlitePool,
     environment: &'static CachedEnvironment,
+    settings: Arc<Settings>,
+    photos: Arc<PhotoStore>,
 }
 
 impl AppState {
@@ -56,9 +79,7 @@ fn cache_templates() -> bool {
 }
 
 fn create_database_if_not_exists(db_url: &str) {
-    let regex = Regex::new("sqlite://(.*)").unwrap();
-    if let Some(m) = regex.captures(db_url) {
-        let path = Path::new(m.get(1).unwrap().as_str());
+    if let Some(path) = sqlite_path(db_url) {
         if !path.exists() {
             info!("Creating sqlite file {:?}", path);
             File::create(path).expect("Failed to create file");
@@ -68,13 +89,40 @@ fn create_database_if_not_exists(db_url: &str) {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .init();
+    let cli = Cli::parse();
+    let settings = Settings::load(&cli.settings)?;
+    if cli.print_config {
+        print!("{}", settings.to_toml()?);
+        return Ok(());
+    }
 
-    let db_url = std::env::var("DATABASE_URL").expect("Failed to find database url");
-    create_database_if_not_exists(&db_url);
-    let pool = SqlitePoolOptions::new().connect(&db_url).await.unwrap();
+    let command = cli.command.unwrap_or(Command::Serve);
+    if let Command::Serve = command {
+        tracing_subscriber::fmt()
+            .with_max_level(settings.log_level)
+            .init();
+    } else {
+        // Keep stdout for the output of the command, and only mention
+        // problems unless asked for less.
+        tracing_subscriber::fmt()
+            .with_max_level(settings.log_level.min(tracing::Level::WARN))
+            .with_writer(std::io::stderr)
+            .init();
+    }
+
+    if let Some(path) = &settings.config_file {
+        info!("Read settings from {:?}", path);
+    }
+    info!(
+        "Season starts {:02}-{:02}, timezone {}",
+        settings.season_start.month, settings.season_start.day, settings.timezone
+    );
+
+    create_database_if_not_exists(&settings.database_url);
+    let pool = SqlitePoolOptions::new()
+        .connect(&settings.database_url)
+        .await
+        .with_context(|| format!("Failed to open {}", settings.database_url))?;
 
     info!("Running migrations");
     sqlx::migrate!("./migrations")
@@ -83,20 +131,133 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .expect("Failed to run migrations");
     info!("Migrations finished successfully!");
 
+    match command {
+        Command::Serve => serve(pool, settings).await,
+        Command::Migrate => {
+            println!("The database is up to date");
+            Ok(())
+        }
+        command => {
+            let user = cli::find_user(&pool, cli.user.as_deref());
+            let result = match user.await {
+                Ok(user) => {
+                    cli::run(command, &pool, &user, &settings, &mut std::io::stdout()).await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn serve(pool: SqlitePool, settings: Settings) -> Result<(), Box<dyn std::error::Error>> {
+    let templates = AssetSource::new(settings.templates_dir.clone(), "ui/jinja");
+    let static_files = AssetSource::new(settings.static_dir.clone(), "ui/static");
+    info!(
+        "Templates from {:?}, static files from {:?}",
+        templates, static_files
+    );
     let environment = Box::leak(Box::new(templates::CachedEnvironment::new(
+        templates,
         cache_templates(),
     )));
 
+    let data_dir = settings.data_dir();
+    info!("Storing uploads in {:?}", data_dir);
+    let photos = PhotoStore::new(&data_dir)?;
+    let address_port = settings.listen;
+    let upload_limit = settings.max_upload_mb * 1024 * 1024;
+
+    let state = AppState {
+        pool,
+        environment,
+        settings: Arc::new(settings),
+        photos: Arc::new(photos),
+    };
+
     let app = Router::new()
         .route("/", get(routes::get_index))
+        .route("/login", get(auth::get_login).post(auth::post_login))
+        .route(
+            "/register",
+            get(auth::get_register).post(auth::post_register),
+        )
+        .route("/logout", post(auth::post_logout))
+        .route("/season/:year", get(routes::get_season))
+        .route("/all", get(routes::get_all))
+        .route("/calendar", get(routes::get_calendar))
+        .route("/calendar/:year/:month", get(routes::get_calendar_month))
+        .route("/search", get(routes::get_search))
+        .route("/stats", get(routes::get_stats))
+        .route("/stats/:year", get(routes::get_season_stats))
         .route("/edit", get(routes::get_add))
-        .route("/edit", post(routes::post_edit))
+        .route(
+            "/edit",
+            post(routes::post_edit).layer(DefaultBodyLimit::max(upload_limit)),
+        )
         .route("/activity/:id/delete", post(routes::post_delete))
-        .nest_service("/static", ServeDir::new("ui/static"))
+        .route("/activity/:id/restore", post(routes::post_restore))
+        .route("/activity/:id/history", get(routes::get_history))
+        .route("/revisions/:id/revert", post(routes::post_revert))
+        .route("/trash", get(routes::get_trash))
+        .route("/trash/empty", post(routes::post_empty_trash))
+        .route("/trash/:id/purge", post(routes::post_purge))
+        .route("/activity/:id/track", get(routes::get_track_file))
+        .route("/photos/:id", get(routes::get_photo_file))
+        .route("/photos/:id/thumbnail", get(routes::get_photo_thumbnail))
+        .route("/photos/:id/delete", post(routes::post_delete_photo))
+        .route("/locations", get(routes::get_locations_page))
+        .route(
+            "/locations/edit",
+            get(routes::get_edit_location).post(routes::post_edit_location),
+        )
+        .route("/locations/:id/delete", post(routes::post_delete_location))
+        .route("/types", get(routes::get_types_page))
+        .route(
+            "/types/edit",
+            get(routes::get_edit_type).post(routes::post_edit_type),
+        )
+        .route("/types/:id/delete", post(routes::post_delete_type))
+        .route("/entry-templates", get(routes::get_entry_templates_page))
+        .route(
+            "/entry-templates/edit",
+            get(routes::get_edit_entry_template).post(routes::post_edit_entry_template),
+        )
+        .route(
+            "/entry-templates/:id/delete",
+            post(routes::post_delete_entry_template),
+        )
+        .route("/export.csv", get(routes::get_export))
+        .route(routes::CALENDAR_FEED_PATH, get(routes::get_calendar_feed))
+        .route("/settings", get(routes::get_settings_page))
+        .route("/settings/feed-token", post(routes::post_feed_token))
+        .route("/import", get(routes::get_import).post(routes::post_import))
+        .route(
+            "/api/v1/activities",
+            get(api::list_activities).post(api::create_activity),
+        )
+        .route(
+            "/api/v1/activities/:id",
+            get(api::get_activity_by_id)
+                .put(api::put_activity)
+                .delete(api::remove_activity),
+        );
+    let app = match static_files {
+        AssetSource::Disk(dir) => app.nest_service("/static", ServeDir::new(dir)),
+        AssetSource::Embedded => app.route("/static/*path", get(assets::get_static)),
+    };
+    let app = app
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            error::render_error_pages,
+        ))
         .layer(middleware::from_fn(logging_layer))
-        .with_state(AppState { pool, environment });
+        .with_state(state);
 
-    let address_port: SocketAddr = "0.0.0.0:8080".parse().unwrap();
     let s = axum::Server::bind(&address_port)
         .serve(app.into_make_service())
         .with_graceful_shutdown(async {
@@ -1,10 +1,7 @@
-use anyhow::{self};
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
-use chrono_tz::Europe::Oslo;
+use anyhow::{self, Context};
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, SqlitePool};
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use sqlx::{FromRow, SqliteConnection, SqlitePool};
 
 #[derive(Debug, Serialize, FromRow)]
 pub struct ActivityRow {
@@ -13,8 +10,23 @@ pub struct ActivityRow {
     pub location: String,
     pub duration_hours: Option<f64>,
     pub r#type: String,
+    pub type_color: Option<String>,
+    pub type_icon: Option<String>,
     pub score: Option<f64>,
     pub description: String,
+    pub distance_km: Option<f64>,
+    pub vertical_drop_m: Option<f64>,
+    pub max_speed_kmh: Option<f64>,
+    pub runs: Option<i64>,
+}
+
+/// Metrics derived from a GPX or FIT track.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrackStats {
+    pub distance_km: f64,
+    pub vertical_drop_m: f64,
+    pub max_speed_kmh: f64,
+    pub runs: i64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -25,32 +37,75 @@ pub struct Activity {
     pub duration_hours: Option<f64>,
     pub r#type: String,
     pub type_color: Option<String>,
+    pub type_icon: Option<String>,
     pub score: Option<f64>,
     pub description: String,
+    /// Set when a track has been uploaded. Ignored when saving an activity,
+    /// see [`attach_track`].
+    #[serde(default)]
+    pub track: Option<TrackStats>,
+}
+
+/// Parses a date as stored in the `activities` table. Dates are written by sqlx
+/// with a space as separator, but older rows could use `T`.
+pub fn parse_stored_date(value: &str) -> anyhow::Result<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f"))
+        .with_context(|| format!("Date time '{}' is not valid", value))
 }
 
-impl From<ActivityRow> for Activity {
-    fn from(value: ActivityRow) -> Self {
-        let date = NaiveDateTime::parse_from_str(&value.date, "%Y-%m-%d %H:%M:%S")
-            .expect("Date time is not valid");
+impl TryFrom<ActivityRow> for Activity {
+    type Error = anyhow::Error;
 
-        Activity {
+    fn try_from(value: ActivityRow) -> anyhow::Result<Self> {
+        let date = parse_stored_date(&value.date)
+            .with_context(|| format!("Activity {} has an invalid date", value.id))?;
+
+        Ok(Activity {
             id: Some(value.id),
             date,
             location: value.location,
             duration_hours: value.duration_hours,
-            type_color: Some(string_to_rgb(&value.r#type)),
+            type_color: Some(
+                value
+                    .type_color
+                    .unwrap_or_else(|| string_to_rgb(&value.r#type)),
+            ),
+            type_icon: value.type_icon.filter(|icon| !icon.is_empty()),
             r#type: value.r#type,
             score: value.score,
             description: value.description,
-        }
+            track: match (
+                value.distance_km,
+                value.vertical_drop_m,
+                value.max_speed_kmh,
+                value.runs,
+            ) {
+                (Some(distance_km), Some(vertical_drop_m), Some(max_speed_kmh), Some(runs)) => {
+                    Some(TrackStats {
+                        distance_km,
+                        vertical_drop_m,
+                        max_speed_kmh,
+                        runs,
+                    })
+                }
+                _ => None,
+            },
+        })
     }
 }
 
+/// 64 bit FNV-1a. Unlike `DefaultHasher` the result is the same on every Rust
+/// release, so fallback colours don't change after an upgrade.
+fn fnv1a(input: &str) -> u64 {
+    input.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The default colour for a type that hasn't been given one.
 pub fn string_to_rgb(input: &str) -> String {
-    let mut hasher = DefaultHasher::new();
-    input.hash(&mut hasher);
-    let hash = hasher.finish();
+    let hash = fnv1a(input);
 
     let mut r = (hash & 0xFF) as u8;
     let mut g = ((hash >> 8) & 0xFF) as u8;
@@ -71,144 +126,1032 @@ pub fn string_to_rgb(input: &str) -> String {
     format!("#{:02X}{:02X}{:02X}", r, g, b)
 }
 
+/// The position of an activity in the newest first order of
+/// [`get_activities_from`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Cursor {
+    pub date: NaiveDateTime,
+    pub id: i64,
+}
+
+impl Cursor {
+    pub fn of(activity: &Activity) -> Option<Cursor> {
+        Some(Cursor {
+            date: activity.date,
+            id: activity.id?,
+        })
+    }
+}
+
+/// At most `limit` activities, starting after `after`.
+#[derive(Debug, Clone, Copy)]
+pub struct Page {
+    pub after: Option<Cursor>,
+    pub limit: i64,
+}
+
+/// Narrows down [`get_activities_from`] and [`get_summary`]. Empty lists and
+/// missing bounds match everything, and the bounds are inclusive.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ActivityFilter {
+    pub types: Vec<String>,
+    pub locations: Vec<String>,
+    pub min_score: Option<f64>,
+    pub max_score: Option<f64>,
+    pub min_duration: Option<f64>,
+    pub max_duration: Option<f64>,
+}
+
+/// A JSON array for `json_each`, or `None` when there is nothing to match.
+fn json_list(values: &[String]) -> Option<String> {
+    (!values.is_empty()).then(|| serde_json::to_string(values).expect("Strings serialize"))
+}
+
+/// Activities between `from` and `to`, newest first. Pass a [`Page`] to only
+/// get some of them. Pages are keyed on date and id, so activities that are
+/// added while paging don't shift the following pages.
 pub async fn get_activities_from(
     con: &SqlitePool,
-    from: NaiveDateTime,
+    owner_id: i64,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    filter: &ActivityFilter,
+    page: Option<Page>,
 ) -> anyhow::Result<Vec<Activity>> {
-    let timestamp = from
-        .and_local_timezone(Oslo)
-        .earliest()
-        .unwrap()
-        .timestamp();
-
+    let types = json_list(&filter.types);
+    let locations = json_list(&filter.locations);
+    let after_date = page.and_then(|x| x.after).map(|x| x.date);
+    let after_id = page.and_then(|x| x.after).map(|x| x.id);
+    let limit = page.map(|x| x.limit);
     let response = sqlx::query_as!(
         ActivityRow,
         r#"select
-            sa.id,
+            sa.id as "id!",
             sa.date,
-            coalesce(sa.location, '') as location,
+            coalesce(sa.location, '') as "location!: String",
             sa.duration_hours,
             sa.type as type,
+            t.color as type_color,
+            t.icon as "type_icon: String",
             sa.description,
-            sa.score
+            sa.score,
+            sa.distance_km,
+            sa.vertical_drop_m,
+            sa.max_speed_kmh,
+            sa.runs
             from activities as sa
-            where date >= ?
-            order by date desc"#,
-        timestamp,
+            left join activity_types as t on t.owner_id = sa.owner_id and t.name = sa.type
+            where sa.owner_id = ?1 and sa.deleted_at is null
+                and (?2 is null or date >= ?2) and (?3 is null or date < ?3)
+                and (
+                    ?4 is null
+                    or date < ?4
+                    or (date = ?4 and sa.id < ?5)
+                )
+                and (?7 is null or sa.type in (select value from json_each(?7)))
+                and (?8 is null or sa.location in (select value from json_each(?8)))
+                and (?9 is null or sa.score >= ?9)
+                and (?10 is null or sa.score <= ?10)
+                and (?11 is null or sa.duration_hours >= ?11)
+                and (?12 is null or sa.duration_hours <= ?12)
+            order by date desc, sa.id desc
+            limit coalesce(?6, -1)"#,
+        owner_id,
+        from,
+        to,
+        after_date,
+        after_id,
+        limit,
+        types,
+        locations,
+        filter.min_score,
+        filter.max_score,
+        filter.min_duration,
+        filter.max_duration,
     )
     .fetch_all(con)
     .await?;
 
-    Ok(response.into_iter().map(|x| x.into()).collect())
+    response.into_iter().map(Activity::try_from).collect()
 }
 
-pub async fn get_activity(con: &SqlitePool, id: i64) -> anyhow::Result<Option<Activity>> {
+pub async fn get_activity(
+    con: &SqlitePool,
+    owner_id: i64,
+    id: i64,
+) -> anyhow::Result<Option<Activity>> {
     let response = sqlx::query_as!(
         ActivityRow,
-        "select
-            sa.id,
+        r#"select
+            sa.id as "id!",
             sa.date,
-            coalesce(sa.location, '') as location,
+            coalesce(sa.location, '') as "location!: String",
             sa.duration_hours,
             sa.type,
+            t.color as type_color,
+            t.icon as "type_icon: String",
             sa.description,
-            sa.score
+            sa.score,
+            sa.distance_km,
+            sa.vertical_drop_m,
+            sa.max_speed_kmh,
+            sa.runs
             from activities as sa
-            where sa.id == ?",
+            left join activity_types as t on t.owner_id = sa.owner_id and t.name = sa.type
+            where sa.id == ? and sa.owner_id = ? and sa.deleted_at is null"#,
         id,
+        owner_id,
     )
     .fetch_optional(con)
     .await?;
 
-    Ok(response.map(|x| x.into()))
+    response.map(Activity::try_from).transpose()
 }
 
-#[derive(Debug, Serialize, FromRow)]
-pub struct ActivityType {
-    pub r#type: String,
+const SNIPPET_START: char = '\u{1}';
+const SNIPPET_END: char = '\u{2}';
+
+#[derive(Debug, FromRow)]
+struct SearchRow {
+    id: i64,
+    date: String,
+    location: String,
+    duration_hours: Option<f64>,
+    r#type: String,
+    type_color: Option<String>,
+    type_icon: Option<String>,
+    score: Option<f64>,
+    description: String,
+    distance_km: Option<f64>,
+    vertical_drop_m: Option<f64>,
+    max_speed_kmh: Option<f64>,
+    runs: Option<i64>,
+    snippet: String,
 }
 
-pub async fn get_all_types(
-    con: &SqlitePool,
-    start: Option<NaiveDateTime>,
-    end: Option<NaiveDateTime>,
-) -> anyhow::Result<Vec<String>> {
-    let start = start.unwrap_or(NaiveDateTime::new(
-        NaiveDate::from_yo_opt(1970, 1).unwrap(),
-        NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-    ));
-    let end = end.unwrap_or(NaiveDateTime::new(
-        NaiveDate::from_yo_opt(2050, 1).unwrap(),
-        NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-    ));
-    let results = sqlx::query_as!(
-        ActivityType,
-        "select distinct type
-        from activities
-        where date >= ? and date <= ?
-        order by 1",
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    pub activity: Activity,
+    /// HTML with the matched terms wrapped in `<mark>`.
+    pub snippet: String,
+}
+
+/// Turns user input into an FTS5 query where every word is matched as a
+/// prefix, so stray quotes or operators can't produce syntax errors.
+fn fts_query(input: &str) -> String {
+    input
+        .split_whitespace()
+        .map(|word| word.replace('"', ""))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn highlight_snippet(snippet: &str) -> String {
+    minijinja::HtmlEscape(snippet)
+        .to_string()
+        .replace(SNIPPET_START, "<mark>")
+        .replace(SNIPPET_END, "</mark>")
+}
+
+/// Full text search in description, location and type, best matches first.
+pub async fn search_activities(
+    con: &SqlitePool,
+    owner_id: i64,
+    query: &str,
+    limit: i64,
+) -> anyhow::Result<Vec<SearchResult>> {
+    let query = fts_query(query);
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+    let start = SNIPPET_START.to_string();
+    let end = SNIPPET_END.to_string();
+
+    let rows = sqlx::query_as!(
+        SearchRow,
+        r#"select
+            a.id as "id!",
+            a.date as "date!",
+            coalesce(a.location, '') as "location!: String",
+            a.duration_hours,
+            a.type as "type!",
+            t.color as type_color,
+            t.icon as "type_icon: String",
+            a.description as "description!",
+            a.score,
+            a.distance_km,
+            a.vertical_drop_m,
+            a.max_speed_kmh,
+            a.runs,
+            coalesce(snippet(activities_fts, -1, ?, ?, '…', 24), '') as "snippet!: String"
+        from activities_fts
+        join activities as a on a.id = activities_fts.rowid
+        left join activity_types as t on t.owner_id = a.owner_id and t.name = a.type
+        where activities_fts match ? and a.owner_id = ? and a.deleted_at is null
+        order by rank
+        limit ?"#,
         start,
         end,
+        query,
+        owner_id,
+        limit,
     )
     .fetch_all(con)
     .await?;
-    Ok(results.into_iter().map(|x| x.r#type).collect())
-}
-
-pub async fn get_all_locations(
-    con: &SqlitePool,
-    start: Option<NaiveDateTime>,
-    end: Option<NaiveDateTime>,
-) -> anyhow::Result<Vec<String>> {
-    let start = start.unwrap_or(NaiveDateTime::new(
-        NaiveDate::from_yo_opt(1970, 1).unwrap(),
-        NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-    ));
-    let end = end.unwrap_or(NaiveDateTime::new(
-        NaiveDate::from_yo_opt(2050, 1).unwrap(),
-        NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-    ));
-    let results = sqlx::query_as!(
-        ActivityType,
-        "select distinct coalesce(location, '') as type
+
+    rows.into_iter()
+        .map(|row| {
+            let snippet = highlight_snippet(&row.snippet);
+            let activity = Activity::try_from(ActivityRow {
+                id: row.id,
+                date: row.date,
+                location: row.location,
+                duration_hours: row.duration_hours,
+                r#type: row.r#type,
+                type_color: row.type_color,
+                type_icon: row.type_icon,
+                score: row.score,
+                description: row.description,
+                distance_km: row.distance_km,
+                vertical_drop_m: row.vertical_drop_m,
+                max_speed_kmh: row.max_speed_kmh,
+                runs: row.runs,
+            })?;
+            Ok(SearchResult { activity, snippet })
+        })
+        .collect()
+}
+
+/// Returns every distinct day that has at least one activity.
+pub async fn get_activity_dates(con: &SqlitePool, owner_id: i64) -> anyhow::Result<Vec<NaiveDate>> {
+    let days = sqlx::query_scalar!(
+        r#"select distinct substr(date, 1, 10) as "day!: String"
         from activities
-        where date >= ? and date <= ? and location != null
-        order by 1",
-        start,
-        end,
+        where owner_id = ? and deleted_at is null
+        order by 1"#,
+        owner_id,
+    )
+    .fetch_all(con)
+    .await?;
+
+    Ok(days
+        .iter()
+        .filter_map(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+        .collect())
+}
+
+/// Names of the types that aren't archived, in the user's chosen order.
+pub async fn get_all_types(con: &SqlitePool, owner_id: i64) -> anyhow::Result<Vec<String>> {
+    let names = sqlx::query_scalar!(
+        "select name from activity_types
+        where owner_id = ? and not archived
+        order by sort_order, name",
+        owner_id,
+    )
+    .fetch_all(con)
+    .await?;
+    Ok(names)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ActivityType {
+    pub id: Option<i64>,
+    pub name: String,
+    pub color: String,
+    pub icon: String,
+    pub sort_order: i64,
+    pub archived: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityTypeUsage {
+    #[serde(flatten)]
+    pub activity_type: ActivityType,
+    pub activities: i64,
+}
+
+/// Returns every type, archived ones included, with the number of activities
+/// of that type.
+pub async fn get_activity_types(
+    con: &SqlitePool,
+    owner_id: i64,
+) -> anyhow::Result<Vec<ActivityTypeUsage>> {
+    let rows = sqlx::query!(
+        r#"
+            select
+                t.id as "id!",
+                t.name,
+                t.color,
+                t.icon,
+                t.sort_order,
+                t.archived as "archived: bool",
+                (
+                    select count(*) from activities as a
+                    where a.owner_id = t.owner_id and a.type = t.name and a.deleted_at is null
+                ) as "activities!: i64"
+            from activity_types as t
+            where t.owner_id = ?
+            order by t.archived, t.sort_order, t.name
+        "#,
+        owner_id,
+    )
+    .fetch_all(con)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ActivityTypeUsage {
+            activity_type: ActivityType {
+                id: Some(row.id),
+                color: row.color.unwrap_or_else(|| string_to_rgb(&row.name)),
+                name: row.name,
+                icon: row.icon,
+                sort_order: row.sort_order,
+                archived: row.archived,
+            },
+            activities: row.activities,
+        })
+        .collect())
+}
+
+pub async fn get_activity_type(
+    con: &SqlitePool,
+    owner_id: i64,
+    id: i64,
+) -> anyhow::Result<Option<ActivityType>> {
+    let row = sqlx::query!(
+        r#"
+            select
+                id as "id!",
+                name,
+                color,
+                icon,
+                sort_order,
+                archived as "archived: bool"
+            from activity_types
+            where id = ? and owner_id = ?
+        "#,
+        id,
+        owner_id,
+    )
+    .fetch_optional(con)
+    .await?;
+
+    Ok(row.map(|row| ActivityType {
+        id: Some(row.id),
+        color: row.color.unwrap_or_else(|| string_to_rgb(&row.name)),
+        name: row.name,
+        icon: row.icon,
+        sort_order: row.sort_order,
+        archived: row.archived,
+    }))
+}
+
+/// Finds the id of the type called `name`, ignoring case.
+pub async fn find_activity_type_id(
+    con: &SqlitePool,
+    owner_id: i64,
+    name: &str,
+) -> anyhow::Result<Option<i64>> {
+    let id = sqlx::query_scalar!(
+        r#"select id as "id!" from activity_types where owner_id = ? and name = ?"#,
+        owner_id,
+        name,
+    )
+    .fetch_optional(con)
+    .await?;
+    Ok(id)
+}
+
+/// Creates or updates a type. Renaming a type renames it on every activity of
+/// that type as well.
+pub async fn save_activity_type(
+    con: &SqlitePool,
+    owner_id: i64,
+    activity_type: ActivityType,
+) -> anyhow::Result<i64> {
+    let mut tx = con.begin().await?;
+    let id = match activity_type.id {
+        None => sqlx::query!(
+            r"
+                insert into activity_types (
+                    owner_id, name, color, icon, sort_order, archived
+                ) values (?, ?, ?, ?, ?, ?)
+            ",
+            owner_id,
+            activity_type.name,
+            activity_type.color,
+            activity_type.icon,
+            activity_type.sort_order,
+            activity_type.archived,
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid(),
+        Some(id) => {
+            let old_name = sqlx::query_scalar!(
+                "select name from activity_types where id = ? and owner_id = ?",
+                id,
+                owner_id,
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .with_context(|| format!("Type {} does not exist", id))?;
+
+            sqlx::query!(
+                r"
+                    update activity_types
+                        set name = ?,
+                            color = ?,
+                            icon = ?,
+                            sort_order = ?,
+                            archived = ?
+                        where id = ? and owner_id = ?
+                ",
+                activity_type.name,
+                activity_type.color,
+                activity_type.icon,
+                activity_type.sort_order,
+                activity_type.archived,
+                id,
+                owner_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "update activities set type = ? where owner_id = ? and type = ?",
+                activity_type.name,
+                owner_id,
+                old_name,
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "update activity_revisions set type = ? where owner_id = ? and type = ?",
+                activity_type.name,
+                owner_id,
+                old_name,
+            )
+            .execute(&mut *tx)
+            .await?;
+            id
+        }
+    };
+    tx.commit().await?;
+    Ok(id)
+}
+
+pub async fn delete_activity_type(con: &SqlitePool, owner_id: i64, id: i64) -> anyhow::Result<()> {
+    sqlx::query!(
+        "delete from activity_types where id = ? and owner_id = ?",
+        id,
+        owner_id
+    )
+    .execute(con)
+    .await?;
+    Ok(())
+}
+
+/// A named starting point for the add form.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EntryTemplate {
+    pub id: Option<i64>,
+    pub name: String,
+    pub r#type: String,
+    pub location: String,
+    pub duration_hours: Option<f64>,
+    pub description: String,
+    pub is_default: bool,
+}
+
+impl EntryTemplate {
+    /// A new activity at `date` filled in from the template.
+    pub fn activity(&self, date: NaiveDateTime) -> Activity {
+        Activity {
+            id: None,
+            date,
+            location: self.location.clone(),
+            duration_hours: self.duration_hours,
+            r#type: self.r#type.clone(),
+            type_color: None,
+            type_icon: None,
+            score: None,
+            description: self.description.clone(),
+            track: None,
+        }
+    }
+}
+
+pub async fn get_entry_templates(
+    con: &SqlitePool,
+    owner_id: i64,
+) -> anyhow::Result<Vec<EntryTemplate>> {
+    let templates = sqlx::query_as!(
+        EntryTemplate,
+        r#"
+            select
+                id as "id: i64",
+                name,
+                type as "type",
+                location,
+                duration_hours,
+                description,
+                is_default as "is_default: bool"
+            from entry_templates
+            where owner_id = ?
+            order by name
+        "#,
+        owner_id,
+    )
+    .fetch_all(con)
+    .await?;
+    Ok(templates)
+}
+
+pub async fn get_entry_template(
+    con: &SqlitePool,
+    owner_id: i64,
+    id: i64,
+) -> anyhow::Result<Option<EntryTemplate>> {
+    Ok(get_entry_templates(con, owner_id)
+        .await?
+        .into_iter()
+        .find(|x| x.id == Some(id)))
+}
+
+/// The template the add form starts from when none is picked.
+pub async fn get_default_entry_template(
+    con: &SqlitePool,
+    owner_id: i64,
+) -> anyhow::Result<Option<EntryTemplate>> {
+    Ok(get_entry_templates(con, owner_id)
+        .await?
+        .into_iter()
+        .find(|x| x.is_default))
+}
+
+pub async fn find_entry_template_id(
+    con: &SqlitePool,
+    owner_id: i64,
+    name: &str,
+) -> anyhow::Result<Option<i64>> {
+    let id = sqlx::query_scalar!(
+        r#"select id as "id!" from entry_templates where owner_id = ? and name = ?"#,
+        owner_id,
+        name,
+    )
+    .fetch_optional(con)
+    .await?;
+    Ok(id)
+}
+
+/// Inserts or updates the template. Only one template can be the default, so
+/// making this one the default clears it on the others.
+pub async fn save_entry_template(
+    con: &SqlitePool,
+    owner_id: i64,
+    template: EntryTemplate,
+) -> anyhow::Result<i64> {
+    let mut tx = con.begin().await?;
+    if template.is_default {
+        sqlx::query!(
+            "update entry_templates set is_default = false where owner_id = ?",
+            owner_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    let id = match template.id {
+        None => sqlx::query!(
+            r"
+                insert into entry_templates (
+                    owner_id, name, type, location, duration_hours, description, is_default
+                ) values (?, ?, ?, ?, ?, ?, ?)
+            ",
+            owner_id,
+            template.name,
+            template.r#type,
+            template.location,
+            template.duration_hours,
+            template.description,
+            template.is_default,
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid(),
+        Some(id) => {
+            sqlx::query!(
+                r"
+                    update entry_templates
+                        set name = ?,
+                            type = ?,
+                            location = ?,
+                            duration_hours = ?,
+                            description = ?,
+                            is_default = ?
+                        where id = ? and owner_id = ?
+                ",
+                template.name,
+                template.r#type,
+                template.location,
+                template.duration_hours,
+                template.description,
+                template.is_default,
+                id,
+                owner_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            id
+        }
+    };
+    tx.commit().await?;
+    Ok(id)
+}
+
+pub async fn delete_entry_template(con: &SqlitePool, owner_id: i64, id: i64) -> anyhow::Result<()> {
+    sqlx::query!(
+        "delete from entry_templates where id = ? and owner_id = ?",
+        id,
+        owner_id
+    )
+    .execute(con)
+    .await?;
+    Ok(())
+}
+
+/// Adds `name` to the types table unless it's already there, and returns the
+/// name as spelled in the table. New types get their default colour stored so
+/// it stays the same from then on.
+async fn ensure_activity_type(
+    con: &mut SqliteConnection,
+    owner_id: i64,
+    name: &str,
+) -> anyhow::Result<String> {
+    let name = name.trim();
+    let color = string_to_rgb(name);
+    sqlx::query!(
+        "insert or ignore into activity_types (owner_id, name, color, sort_order)
+        values (
+            ?1, ?2, ?3,
+            (select coalesce(max(sort_order) + 1, 0) from activity_types where owner_id = ?1)
+        )",
+        owner_id,
+        name,
+        color,
+    )
+    .execute(&mut *con)
+    .await?;
+    let stored = sqlx::query_scalar!(
+        "select name from activity_types where owner_id = ? and name = ?",
+        owner_id,
+        name,
+    )
+    .fetch_one(&mut *con)
+    .await?;
+    Ok(stored)
+}
+
+pub async fn get_all_locations(con: &SqlitePool, owner_id: i64) -> anyhow::Result<Vec<String>> {
+    let names = sqlx::query_scalar!(
+        "select name from locations where owner_id = ? order by name",
+        owner_id,
+    )
+    .fetch_all(con)
+    .await?;
+    Ok(names)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Location {
+    pub id: Option<i64>,
+    pub name: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub elevation_m: Option<f64>,
+    pub country: String,
+    pub notes: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocationUsage {
+    #[serde(flatten)]
+    pub location: Location,
+    pub activities: i64,
+}
+
+/// Returns every location with the number of activities logged there.
+pub async fn get_locations(con: &SqlitePool, owner_id: i64) -> anyhow::Result<Vec<LocationUsage>> {
+    let rows = sqlx::query!(
+        r#"
+            select
+                l.id as "id!",
+                l.name,
+                l.latitude,
+                l.longitude,
+                l.elevation_m,
+                l.country,
+                l.notes,
+                (
+                    select count(*) from activities as a
+                    where a.owner_id = l.owner_id and a.location = l.name
+                        and a.deleted_at is null
+                ) as "activities!: i64"
+            from locations as l
+            where l.owner_id = ?
+            order by l.name
+        "#,
+        owner_id,
     )
     .fetch_all(con)
     .await?;
-    Ok(results.into_iter().map(|x| x.r#type).collect())
+
+    Ok(rows
+        .into_iter()
+        .map(|row| LocationUsage {
+            location: Location {
+                id: Some(row.id),
+                name: row.name,
+                latitude: row.latitude,
+                longitude: row.longitude,
+                elevation_m: row.elevation_m,
+                country: row.country,
+                notes: row.notes,
+            },
+            activities: row.activities,
+        })
+        .collect())
+}
+
+pub async fn get_location(
+    con: &SqlitePool,
+    owner_id: i64,
+    id: i64,
+) -> anyhow::Result<Option<Location>> {
+    let row = sqlx::query!(
+        r#"
+            select id as "id!", name, latitude, longitude, elevation_m, country, notes
+            from locations
+            where id = ? and owner_id = ?
+        "#,
+        id,
+        owner_id,
+    )
+    .fetch_optional(con)
+    .await?;
+
+    Ok(row.map(|row| Location {
+        id: Some(row.id),
+        name: row.name,
+        latitude: row.latitude,
+        longitude: row.longitude,
+        elevation_m: row.elevation_m,
+        country: row.country,
+        notes: row.notes,
+    }))
+}
+
+/// Finds the id of the location called `name`, ignoring case.
+pub async fn find_location_id(
+    con: &SqlitePool,
+    owner_id: i64,
+    name: &str,
+) -> anyhow::Result<Option<i64>> {
+    let id = sqlx::query_scalar!(
+        r#"select id as "id!" from locations where owner_id = ? and name = ?"#,
+        owner_id,
+        name,
+    )
+    .fetch_optional(con)
+    .await?;
+    Ok(id)
+}
+
+/// Creates or updates a location. Renaming a location renames it on every
+/// activity logged there as well.
+pub async fn save_location(
+    con: &SqlitePool,
+    owner_id: i64,
+    location: Location,
+) -> anyhow::Result<i64> {
+    let mut tx = con.begin().await?;
+    let id = match location.id {
+        None => sqlx::query!(
+            r"
+                insert into locations (
+                    owner_id, name, latitude, longitude, elevation_m, country, notes
+                ) values (?, ?, ?, ?, ?, ?, ?)
+            ",
+            owner_id,
+            location.name,
+            location.latitude,
+            location.longitude,
+            location.elevation_m,
+            location.country,
+            location.notes,
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid(),
+        Some(id) => {
+            let old_name = sqlx::query_scalar!(
+                "select name from locations where id = ? and owner_id = ?",
+                id,
+                owner_id,
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .with_context(|| format!("Location {} does not exist", id))?;
+
+            sqlx::query!(
+                r"
+                    update locations
+                        set name = ?,
+                            latitude = ?,
+                            longitude = ?,
+                            elevation_m = ?,
+                            country = ?,
+                            notes = ?
+                        where id = ? and owner_id = ?
+                ",
+                location.name,
+                location.latitude,
+                location.longitude,
+                location.elevation_m,
+                location.country,
+                location.notes,
+                id,
+                owner_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "update activities set location = ? where owner_id = ? and location = ?",
+                location.name,
+                owner_id,
+                old_name,
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "update activity_revisions set location = ? where owner_id = ? and location = ?",
+                location.name,
+                owner_id,
+                old_name,
+            )
+            .execute(&mut *tx)
+            .await?;
+            id
+        }
+    };
+    tx.commit().await?;
+    Ok(id)
+}
+
+pub async fn delete_location(con: &SqlitePool, owner_id: i64, id: i64) -> anyhow::Result<()> {
+    sqlx::query!(
+        "delete from locations where id = ? and owner_id = ?",
+        id,
+        owner_id
+    )
+    .execute(con)
+    .await?;
+    Ok(())
 }
 
-pub async fn insert_activity(con: &SqlitePool, activity: Activity) -> anyhow::Result<()> {
+/// Adds `name` to the locations table unless it's already there, and returns
+/// the name as spelled in the table so that activities use consistent names.
+async fn ensure_location(
+    con: &mut SqliteConnection,
+    owner_id: i64,
+    name: &str,
+) -> anyhow::Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(String::new());
+    }
     sqlx::query!(
+        "insert or ignore into locations (owner_id, name) values (?, ?)",
+        owner_id,
+        name,
+    )
+    .execute(&mut *con)
+    .await?;
+    let stored = sqlx::query_scalar!(
+        "select name from locations where owner_id = ? and name = ?",
+        owner_id,
+        name,
+    )
+    .fetch_one(&mut *con)
+    .await?;
+    Ok(stored)
+}
+
+pub async fn insert_activity(
+    con: &SqlitePool,
+    owner_id: i64,
+    activity: Activity,
+) -> anyhow::Result<i64> {
+    let mut tx = con.begin().await?;
+    let id = insert_activity_row(&mut tx, owner_id, activity).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Inserts all the activities, or none of them if one fails.
+pub async fn insert_activities(
+    con: &SqlitePool,
+    owner_id: i64,
+    activities: Vec<Activity>,
+) -> anyhow::Result<()> {
+    let mut tx = con.begin().await?;
+    for activity in activities {
+        insert_activity_row(&mut tx, owner_id, activity).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn insert_activity_row(
+    con: &mut SqliteConnection,
+    owner_id: i64,
+    activity: Activity,
+) -> anyhow::Result<i64> {
+    let location = ensure_location(con, owner_id, &activity.location).await?;
+    let r#type = ensure_activity_type(con, owner_id, &activity.r#type).await?;
+    let result = sqlx::query!(
         r"
             insert into activities (
+                owner_id,
                 date,
                 location,
                 duration_hours,
                 type,
                 description,
                 score
-            ) VALUES (?, ?, ?, ?, ?, ?)
+            ) VALUES (?, ?, ?, ?, ?, ?, ?)
         ",
+        owner_id,
         activity.date,
-        activity.location,
+        location,
         activity.duration_hours,
-        activity.r#type,
+        r#type,
         activity.description,
         activity.score,
     )
     .execute(con)
     .await?;
 
-    Ok(())
+    Ok(result.last_insert_rowid())
 }
 
-pub async fn update_activity(con: &SqlitePool, activity: Activity) -> anyhow::Result<()> {
-    let id = activity.id.unwrap();
+/// Saves the activity. The version it replaces is kept in the revisions table,
+/// unless nothing changed.
+pub async fn update_activity(
+    con: &SqlitePool,
+    owner_id: i64,
+    activity: Activity,
+    now: NaiveDateTime,
+) -> anyhow::Result<()> {
+    let id = activity
+        .id
+        .context("Can't update an activity without an id")?;
+    let mut tx = con.begin().await?;
+    let location = ensure_location(&mut tx, owner_id, &activity.location).await?;
+    let r#type = ensure_activity_type(&mut tx, owner_id, &activity.r#type).await?;
+    sqlx::query!(
+        r"
+            insert into activity_revisions (
+                activity_id, owner_id, edited_by, edited_at,
+                date, location, duration_hours, type, description, score
+            )
+            select id, owner_id, ?2, ?3,
+                date, coalesce(location, ''), duration_hours, type, description, score
+            from activities
+            where id = ?1 and owner_id = ?2 and deleted_at is null
+                and not (
+                    date is ?4
+                    and coalesce(location, '') is ?5
+                    and duration_hours is ?6
+                    and type is ?7
+                    and description is ?8
+                    and score is ?9
+                )
+        ",
+        id,
+        owner_id,
+        now,
+        activity.date,
+        location,
+        activity.duration_hours,
+        r#type,
+        activity.description,
+        activity.score,
+    )
+    .execute(&mut *tx)
+    .await?;
     sqlx::query!(
         r"
             update activities
@@ -218,34 +1161,541 @@ pub async fn update_activity(con: &SqlitePool, activity: Activity) -> anyhow::Re
                     type = ?,
                     description = ?,
                     score = ?
-                where id = ?
+                where id = ? and owner_id = ? and deleted_at is null
         ",
         activity.date,
-        activity.location,
+        location,
         activity.duration_hours,
-        activity.r#type,
+        r#type,
         activity.description,
         activity.score,
         id,
+        owner_id,
     )
-    .execute(con)
+    .execute(&mut *tx)
     .await?;
+    tx.commit().await?;
 
     Ok(())
 }
 
-pub async fn delete_activity(con: &SqlitePool, id: i64) -> anyhow::Result<()> {
-    sqlx::query!("delete from activities where id = ?", id)
-        .execute(con)
-        .await?;
+/// An earlier version of an activity.
+#[derive(Debug, Serialize)]
+pub struct ActivityRevision {
+    pub id: i64,
+    pub edited_at: NaiveDateTime,
+    /// The user who replaced this version, if they still exist.
+    pub edited_by: Option<String>,
+    pub activity: Activity,
+}
 
-    Ok(())
+#[derive(Debug, FromRow)]
+struct RevisionRow {
+    id: i64,
+    activity_id: i64,
+    edited_at: String,
+    edited_by: Option<String>,
+    date: String,
+    location: String,
+    duration_hours: Option<f64>,
+    r#type: String,
+    description: String,
+    score: Option<f64>,
 }
 
-#[derive(Debug, Serialize, FromRow)]
-pub struct SummaryRow {
-    pub r#type: String,
-    pub days: i64,
+impl TryFrom<RevisionRow> for ActivityRevision {
+    type Error = anyhow::Error;
+
+    fn try_from(row: RevisionRow) -> anyhow::Result<Self> {
+        let activity = Activity::try_from(ActivityRow {
+            id: row.activity_id,
+            date: row.date,
+            location: row.location,
+            duration_hours: row.duration_hours,
+            r#type: row.r#type,
+            type_color: None,
+            type_icon: None,
+            score: row.score,
+            description: row.description,
+            distance_km: None,
+            vertical_drop_m: None,
+            max_speed_kmh: None,
+            runs: None,
+        })?;
+        Ok(ActivityRevision {
+            id: row.id,
+            edited_at: parse_stored_date(&row.edited_at)?,
+            edited_by: row.edited_by,
+            activity,
+        })
+    }
+}
+
+/// Earlier versions of the activity, newest first.
+pub async fn get_activity_revisions(
+    con: &SqlitePool,
+    owner_id: i64,
+    activity_id: i64,
+) -> anyhow::Result<Vec<ActivityRevision>> {
+    let rows = sqlx::query_as!(
+        RevisionRow,
+        r#"select
+            r.id as "id!",
+            r.activity_id,
+            r.edited_at,
+            u.username as "edited_by?",
+            r.date,
+            r.location,
+            r.duration_hours,
+            r.type,
+            r.description,
+            r.score
+        from activity_revisions as r
+        left join users as u on u.id = r.edited_by
+        where r.activity_id = ? and r.owner_id = ?
+        order by r.id desc"#,
+        activity_id,
+        owner_id,
+    )
+    .fetch_all(con)
+    .await?;
+
+    rows.into_iter().map(ActivityRevision::try_from).collect()
+}
+
+/// Puts an earlier version back. The version being replaced becomes a
+/// revision itself, so reverting can be undone as well. Returns the id of the
+/// activity, or `None` if there is no such revision.
+pub async fn revert_activity(
+    con: &SqlitePool,
+    owner_id: i64,
+    revision_id: i64,
+    now: NaiveDateTime,
+) -> anyhow::Result<Option<i64>> {
+    let row = sqlx::query_as!(
+        RevisionRow,
+        r#"select
+            r.id as "id!",
+            r.activity_id,
+            r.edited_at,
+            u.username as "edited_by?",
+            r.date,
+            r.location,
+            r.duration_hours,
+            r.type,
+            r.description,
+            r.score
+        from activity_revisions as r
+        left join users as u on u.id = r.edited_by
+        where r.id = ? and r.owner_id = ?"#,
+        revision_id,
+        owner_id,
+    )
+    .fetch_optional(con)
+    .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let revision = ActivityRevision::try_from(row)?;
+    let id = revision.activity.id.context("Revisions have an activity")?;
+    if get_activity(con, owner_id, id).await?.is_none() {
+        return Ok(None);
+    }
+    update_activity(con, owner_id, revision.activity, now).await?;
+    Ok(Some(id))
+}
+
+/// Stores an uploaded track that isn't attached to an activity yet. Tracks
+/// that were never attached are removed after a day.
+pub async fn save_track(
+    con: &SqlitePool,
+    owner_id: i64,
+    filename: &str,
+    data: &[u8],
+    now: NaiveDateTime,
+) -> anyhow::Result<i64> {
+    let expired = now - chrono::Duration::days(1);
+    sqlx::query!(
+        "delete from tracks where activity_id is null and uploaded_at < ?",
+        expired
+    )
+    .execute(con)
+    .await?;
+
+    let id = sqlx::query!(
+        "insert into tracks (owner_id, filename, data, uploaded_at) values (?, ?, ?, ?)",
+        owner_id,
+        filename,
+        data,
+        now,
+    )
+    .execute(con)
+    .await?
+    .last_insert_rowid();
+    Ok(id)
+}
+
+#[derive(Debug)]
+pub struct Track {
+    pub filename: String,
+    pub data: Vec<u8>,
+}
+
+pub async fn get_track(con: &SqlitePool, owner_id: i64, id: i64) -> anyhow::Result<Option<Track>> {
+    let track = sqlx::query_as!(
+        Track,
+        "select filename, data from tracks where id = ? and owner_id = ?",
+        id,
+        owner_id,
+    )
+    .fetch_optional(con)
+    .await?;
+    Ok(track)
+}
+
+pub async fn get_activity_track(
+    con: &SqlitePool,
+    owner_id: i64,
+    activity_id: i64,
+) -> anyhow::Result<Option<Track>> {
+    let track = sqlx::query_as!(
+        Track,
+        "select filename, data from tracks where activity_id = ? and owner_id = ?",
+        activity_id,
+        owner_id,
+    )
+    .fetch_optional(con)
+    .await?;
+    Ok(track)
+}
+
+/// Makes the track the one belonging to the activity, replacing any earlier
+/// track, and stores the derived metrics on the activity.
+pub async fn attach_track(
+    con: &SqlitePool,
+    owner_id: i64,
+    track_id: i64,
+    activity_id: i64,
+    stats: &TrackStats,
+) -> anyhow::Result<()> {
+    let mut tx = con.begin().await?;
+    sqlx::query!(
+        "delete from tracks where activity_id = ? and owner_id = ? and id != ?",
+        activity_id,
+        owner_id,
+        track_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "update tracks set activity_id = ? where id = ? and owner_id = ?",
+        activity_id,
+        track_id,
+        owner_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r"
+            update activities
+                set distance_km = ?,
+                    vertical_drop_m = ?,
+                    max_speed_kmh = ?,
+                    runs = ?
+                where id = ? and owner_id = ?
+        ",
+        stats.distance_km,
+        stats.vertical_drop_m,
+        stats.max_speed_kmh,
+        stats.runs,
+        activity_id,
+        owner_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Photo {
+    pub id: i64,
+    pub activity_id: i64,
+    /// Name of the files in the photo store.
+    #[serde(skip)]
+    pub key: String,
+    pub filename: String,
+    pub content_type: String,
+}
+
+pub async fn insert_photo(
+    con: &SqlitePool,
+    owner_id: i64,
+    activity_id: i64,
+    key: &str,
+    filename: &str,
+    content_type: &str,
+    now: NaiveDateTime,
+) -> anyhow::Result<i64> {
+    let id = sqlx::query!(
+        r"
+            insert into photos (owner_id, activity_id, key, filename, content_type, uploaded_at)
+            values (?, ?, ?, ?, ?, ?)
+        ",
+        owner_id,
+        activity_id,
+        key,
+        filename,
+        content_type,
+        now,
+    )
+    .execute(con)
+    .await?
+    .last_insert_rowid();
+    Ok(id)
+}
+
+pub async fn get_photo(con: &SqlitePool, owner_id: i64, id: i64) -> anyhow::Result<Option<Photo>> {
+    let photo = sqlx::query_as!(
+        Photo,
+        r#"select id as "id!", activity_id, key, filename, content_type
+        from photos
+        where id = ? and owner_id = ?"#,
+        id,
+        owner_id,
+    )
+    .fetch_optional(con)
+    .await?;
+    Ok(photo)
+}
+
+pub async fn get_activity_photos(
+    con: &SqlitePool,
+    owner_id: i64,
+    activity_id: i64,
+) -> anyhow::Result<Vec<Photo>> {
+    let photos = sqlx::query_as!(
+        Photo,
+        r#"select id as "id!", activity_id, key, filename, content_type
+        from photos
+        where activity_id = ? and owner_id = ?
+        order by id"#,
+        activity_id,
+        owner_id,
+    )
+    .fetch_all(con)
+    .await?;
+    Ok(photos)
+}
+
+/// Photos of the given activities, such as one page of [`get_activities_from`].
+pub async fn get_photos_of(
+    con: &SqlitePool,
+    owner_id: i64,
+    activity_ids: &[i64],
+) -> anyhow::Result<Vec<Photo>> {
+    let ids = serde_json::to_string(activity_ids)?;
+    let photos = sqlx::query_as!(
+        Photo,
+        r#"select p.id as "id!", p.activity_id, p.key, p.filename, p.content_type
+        from photos as p
+        where p.owner_id = ?1 and p.activity_id in (select value from json_each(?2))
+        order by p.id"#,
+        owner_id,
+        ids,
+    )
+    .fetch_all(con)
+    .await?;
+    Ok(photos)
+}
+
+/// Deletes the photo and returns it, so that its files can be removed.
+pub async fn delete_photo(
+    con: &SqlitePool,
+    owner_id: i64,
+    id: i64,
+) -> anyhow::Result<Option<Photo>> {
+    let photo = get_photo(con, owner_id, id).await?;
+    sqlx::query!(
+        "delete from photos where id = ? and owner_id = ?",
+        id,
+        owner_id
+    )
+    .execute(con)
+    .await?;
+    Ok(photo)
+}
+
+/// Moves the activity to the trash. It's left out of every other query until
+/// it's restored with [`restore_activity`].
+pub async fn delete_activity(
+    con: &SqlitePool,
+    owner_id: i64,
+    id: i64,
+    now: NaiveDateTime,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "update activities set deleted_at = ?
+        where id = ? and owner_id = ? and deleted_at is null",
+        now,
+        id,
+        owner_id
+    )
+    .execute(con)
+    .await?;
+
+    Ok(())
+}
+
+/// Takes the activity out of the trash. Its type and location are added back
+/// in case they were removed in the meantime.
+pub async fn restore_activity(con: &SqlitePool, owner_id: i64, id: i64) -> anyhow::Result<bool> {
+    let mut tx = con.begin().await?;
+    let row = sqlx::query!(
+        r#"select type as "type!", coalesce(location, '') as "location!: String"
+        from activities
+        where id = ? and owner_id = ? and deleted_at is not null"#,
+        id,
+        owner_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(row) = row else {
+        return Ok(false);
+    };
+    ensure_activity_type(&mut tx, owner_id, &row.r#type).await?;
+    ensure_location(&mut tx, owner_id, &row.location).await?;
+    sqlx::query!(
+        "update activities set deleted_at = null where id = ? and owner_id = ?",
+        id,
+        owner_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeletedActivity {
+    #[serde(flatten)]
+    pub activity: Activity,
+    pub deleted_at: NaiveDateTime,
+}
+
+/// The activities in the trash, most recently deleted first.
+pub async fn get_deleted_activities(
+    con: &SqlitePool,
+    owner_id: i64,
+) -> anyhow::Result<Vec<DeletedActivity>> {
+    let rows = sqlx::query!(
+        r#"select
+            sa.id as "id!",
+            sa.date,
+            coalesce(sa.location, '') as "location!: String",
+            sa.duration_hours,
+            sa.type,
+            t.color as type_color,
+            t.icon as "type_icon?: String",
+            sa.description,
+            sa.score,
+            sa.distance_km,
+            sa.vertical_drop_m,
+            sa.max_speed_kmh,
+            sa.runs,
+            sa.deleted_at as "deleted_at!"
+            from activities as sa
+            left join activity_types as t on t.owner_id = sa.owner_id and t.name = sa.type
+            where sa.owner_id = ? and sa.deleted_at is not null
+            order by sa.deleted_at desc, sa.id desc"#,
+        owner_id,
+    )
+    .fetch_all(con)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let deleted_at = parse_stored_date(&row.deleted_at)?;
+            let activity = Activity::try_from(ActivityRow {
+                id: row.id,
+                date: row.date,
+                location: row.location,
+                duration_hours: row.duration_hours,
+                r#type: row.r#type,
+                type_color: row.type_color,
+                type_icon: row.type_icon,
+                score: row.score,
+                description: row.description,
+                distance_km: row.distance_km,
+                vertical_drop_m: row.vertical_drop_m,
+                max_speed_kmh: row.max_speed_kmh,
+                runs: row.runs,
+            })?;
+            Ok(DeletedActivity {
+                activity,
+                deleted_at,
+            })
+        })
+        .collect()
+}
+
+/// Removes an activity in the trash for good, and returns its photos so that
+/// their files can be removed. Activities outside the trash are left alone.
+pub async fn purge_activity(
+    con: &SqlitePool,
+    owner_id: i64,
+    id: i64,
+) -> anyhow::Result<Vec<Photo>> {
+    let photos = sqlx::query_as!(
+        Photo,
+        r#"select p.id as "id!", p.activity_id, p.key, p.filename, p.content_type
+        from photos as p
+        join activities as sa on sa.id = p.activity_id
+        where sa.id = ? and sa.owner_id = ? and sa.deleted_at is not null
+        order by p.id"#,
+        id,
+        owner_id,
+    )
+    .fetch_all(con)
+    .await?;
+    sqlx::query!(
+        "delete from activities where id = ? and owner_id = ? and deleted_at is not null",
+        id,
+        owner_id
+    )
+    .execute(con)
+    .await?;
+    Ok(photos)
+}
+
+/// Purges everything in the trash, see [`purge_activity`].
+pub async fn empty_trash(con: &SqlitePool, owner_id: i64) -> anyhow::Result<Vec<Photo>> {
+    let photos = sqlx::query_as!(
+        Photo,
+        r#"select p.id as "id!", p.activity_id, p.key, p.filename, p.content_type
+        from photos as p
+        join activities as sa on sa.id = p.activity_id
+        where sa.owner_id = ? and sa.deleted_at is not null
+        order by p.id"#,
+        owner_id,
+    )
+    .fetch_all(con)
+    .await?;
+    sqlx::query!(
+        "delete from activities where owner_id = ? and deleted_at is not null",
+        owner_id
+    )
+    .execute(con)
+    .await?;
+    Ok(photos)
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct SummaryRow {
+    pub r#type: String,
+    pub type_color: Option<String>,
+    pub type_icon: Option<String>,
+    pub days: i64,
     pub hours: f64,
 }
 
@@ -253,155 +1703,1466 @@ pub struct SummaryRow {
 pub struct Summary {
     pub r#type: String,
     pub type_color: String,
+    pub type_icon: String,
+    pub days: i64,
+    pub hours: f64,
+}
+
+/// Totals per type for the same activities as [`get_activities_from`].
+pub async fn get_summary(
+    con: &SqlitePool,
+    owner_id: i64,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    filter: &ActivityFilter,
+) -> anyhow::Result<Vec<Summary>> {
+    let types = json_list(&filter.types);
+    let locations = json_list(&filter.locations);
+    let response = sqlx::query_as!(
+        SummaryRow,
+        r#"
+            select
+                a.type,
+                t.color as type_color,
+                t.icon as "type_icon: String",
+                count(*) as "days!: i64",
+                coalesce(sum(a.duration_hours), 0.0) as "hours!: f64"
+            from activities as a
+            left join activity_types as t on t.owner_id = a.owner_id and t.name = a.type
+            where a.owner_id = ?1 and a.deleted_at is null
+                and (?2 is null or a.date >= ?2) and (?3 is null or a.date < ?3)
+                and (?4 is null or a.type in (select value from json_each(?4)))
+                and (?5 is null or a.location in (select value from json_each(?5)))
+                and (?6 is null or a.score >= ?6)
+                and (?7 is null or a.score <= ?7)
+                and (?8 is null or a.duration_hours >= ?8)
+                and (?9 is null or a.duration_hours <= ?9)
+            group by a.type
+            order by 4
+        "#,
+        owner_id,
+        from,
+        to,
+        types,
+        locations,
+        filter.min_score,
+        filter.max_score,
+        filter.min_duration,
+        filter.max_duration,
+    )
+    .fetch_all(con)
+    .await?;
+
+    let summaries = response
+        .into_iter()
+        .map(|x| Summary {
+            type_color: x.type_color.unwrap_or_else(|| string_to_rgb(&x.r#type)),
+            type_icon: x.type_icon.unwrap_or_default(),
+            r#type: x.r#type,
+            days: x.days,
+            hours: x.hours,
+        })
+        .collect();
+
+    Ok(summaries)
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonthStats {
+    /// The month as `YYYY-MM`.
+    pub month: String,
     pub days: i64,
     pub hours: f64,
 }
 
-pub async fn get_summary(
-    con: &SqlitePool,
-    from: NaiveDateTime,
-    to: NaiveDateTime,
-) -> anyhow::Result<Vec<Summary>> {
-    let response = sqlx::query_as!(
-        SummaryRow,
-        r"
-            select
-                type,
-                count(*) as days,
-                coalesce(sum(duration_hours), 0.0) as hours
-            from activities
-            where date >= ? and date < ?
-            group by type
-            order by 2
-        ",
-        from,
-        to,
-    )
-    .fetch_all(con)
-    .await?;
+pub async fn get_stats_by_month(
+    con: &SqlitePool,
+    owner_id: i64,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> anyhow::Result<Vec<MonthStats>> {
+    let stats = sqlx::query_as!(
+        MonthStats,
+        r#"
+            select
+                substr(date, 1, 7) as "month!: String",
+                count(distinct substr(date, 1, 10)) as "days!: i64",
+                coalesce(sum(duration_hours), 0.0) as "hours!: f64"
+            from activities
+            where owner_id = ? and deleted_at is null and date >= ? and date < ?
+            group by 1
+            order by 1
+        "#,
+        owner_id,
+        from,
+        to,
+    )
+    .fetch_all(con)
+    .await?;
+    Ok(stats)
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeekdayStats {
+    /// Day of the week as returned by sqlite, where 0 is Sunday.
+    pub weekday: i64,
+    pub days: i64,
+    pub hours: f64,
+}
+
+pub async fn get_stats_by_weekday(
+    con: &SqlitePool,
+    owner_id: i64,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> anyhow::Result<Vec<WeekdayStats>> {
+    let stats = sqlx::query_as!(
+        WeekdayStats,
+        r#"
+            select
+                cast(strftime('%w', substr(date, 1, 10)) as integer) as "weekday!: i64",
+                count(distinct substr(date, 1, 10)) as "days!: i64",
+                coalesce(sum(duration_hours), 0.0) as "hours!: f64"
+            from activities
+            where owner_id = ? and deleted_at is null and date >= ? and date < ?
+            group by 1
+            order by 1
+        "#,
+        owner_id,
+        from,
+        to,
+    )
+    .fetch_all(con)
+    .await?;
+    Ok(stats)
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupStats {
+    pub name: String,
+    pub activities: i64,
+    pub hours: f64,
+    pub average_score: Option<f64>,
+}
+
+pub async fn get_stats_by_type(
+    con: &SqlitePool,
+    owner_id: i64,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> anyhow::Result<Vec<GroupStats>> {
+    let stats = sqlx::query_as!(
+        GroupStats,
+        r#"
+            select
+                type as "name!: String",
+                count(*) as "activities!: i64",
+                coalesce(sum(duration_hours), 0.0) as "hours!: f64",
+                avg(score) as "average_score: f64"
+            from activities
+            where owner_id = ? and deleted_at is null and date >= ? and date < ?
+            group by 1
+            order by 3 desc, 1
+        "#,
+        owner_id,
+        from,
+        to,
+    )
+    .fetch_all(con)
+    .await?;
+    Ok(stats)
+}
+
+pub async fn get_stats_by_location(
+    con: &SqlitePool,
+    owner_id: i64,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> anyhow::Result<Vec<GroupStats>> {
+    let stats = sqlx::query_as!(
+        GroupStats,
+        r#"
+            select
+                location as "name!: String",
+                count(*) as "activities!: i64",
+                coalesce(sum(duration_hours), 0.0) as "hours!: f64",
+                avg(score) as "average_score: f64"
+            from activities
+            where owner_id = ? and deleted_at is null and date >= ? and date < ?
+                and location != ''
+            group by 1
+            order by 3 desc, 1
+        "#,
+        owner_id,
+        from,
+        to,
+    )
+    .fetch_all(con)
+    .await?;
+    Ok(stats)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyTotal {
+    pub date: NaiveDate,
+    pub activities: i64,
+    pub hours: f64,
+    pub average_score: Option<f64>,
+}
+
+/// Totals for every day between `from` and `to` with activities that match
+/// the filter. Days without any are left out.
+pub async fn get_daily_totals(
+    con: &SqlitePool,
+    owner_id: i64,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    filter: &ActivityFilter,
+) -> anyhow::Result<Vec<DailyTotal>> {
+    let types = json_list(&filter.types);
+    let locations = json_list(&filter.locations);
+    let rows = sqlx::query!(
+        r#"
+            select
+                substr(date, 1, 10) as "day!: String",
+                count(*) as "activities!: i64",
+                coalesce(sum(duration_hours), 0.0) as "hours!: f64",
+                avg(score) as "average_score: f64"
+            from activities
+            where owner_id = ?1 and deleted_at is null
+                and (?2 is null or date >= ?2) and (?3 is null or date < ?3)
+                and (?4 is null or type in (select value from json_each(?4)))
+                and (?5 is null or location in (select value from json_each(?5)))
+                and (?6 is null or score >= ?6)
+                and (?7 is null or score <= ?7)
+                and (?8 is null or duration_hours >= ?8)
+                and (?9 is null or duration_hours <= ?9)
+            group by 1
+            order by 1
+        "#,
+        owner_id,
+        from,
+        to,
+        types,
+        locations,
+        filter.min_score,
+        filter.max_score,
+        filter.min_duration,
+        filter.max_duration,
+    )
+    .fetch_all(con)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let date = NaiveDate::parse_from_str(&row.day, "%Y-%m-%d")
+                .with_context(|| format!("Date '{}' is not valid", row.day))?;
+            Ok(DailyTotal {
+                date,
+                activities: row.activities,
+                hours: row.hours,
+                average_score: row.average_score,
+            })
+        })
+        .collect()
+}
+
+/// Hours logged with one type on one day.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TypeDayTotal {
+    pub date: NaiveDate,
+    pub r#type: String,
+    pub type_color: String,
+    pub activities: i64,
+    pub hours: f64,
+}
+
+/// Like [`get_daily_totals`], but with a row for every type on each day.
+pub async fn get_type_day_totals(
+    con: &SqlitePool,
+    owner_id: i64,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    filter: &ActivityFilter,
+) -> anyhow::Result<Vec<TypeDayTotal>> {
+    let types = json_list(&filter.types);
+    let locations = json_list(&filter.locations);
+    let rows = sqlx::query!(
+        r#"
+            select
+                substr(sa.date, 1, 10) as "day!: String",
+                sa.type as "type!: String",
+                t.color as "type_color?: String",
+                count(*) as "activities!: i64",
+                coalesce(sum(sa.duration_hours), 0.0) as "hours!: f64"
+            from activities as sa
+            left join activity_types as t on t.owner_id = sa.owner_id and t.name = sa.type
+            where sa.owner_id = ?1 and sa.deleted_at is null
+                and (?2 is null or sa.date >= ?2) and (?3 is null or sa.date < ?3)
+                and (?4 is null or sa.type in (select value from json_each(?4)))
+                and (?5 is null or sa.location in (select value from json_each(?5)))
+                and (?6 is null or sa.score >= ?6)
+                and (?7 is null or sa.score <= ?7)
+                and (?8 is null or sa.duration_hours >= ?8)
+                and (?9 is null or sa.duration_hours <= ?9)
+            group by 1, 2
+            order by 1, 2
+        "#,
+        owner_id,
+        from,
+        to,
+        types,
+        locations,
+        filter.min_score,
+        filter.max_score,
+        filter.min_duration,
+        filter.max_duration,
+    )
+    .fetch_all(con)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let date = NaiveDate::parse_from_str(&row.day, "%Y-%m-%d")
+                .with_context(|| format!("Date '{}' is not valid", row.day))?;
+            Ok(TypeDayTotal {
+                date,
+                type_color: row.type_color.unwrap_or_else(|| string_to_rgb(&row.r#type)),
+                r#type: row.r#type,
+                activities: row.activities,
+                hours: row.hours,
+            })
+        })
+        .collect()
+}
+
+/// The number of activities of one type with the same score.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScoreCount {
+    pub r#type: String,
+    pub type_color: String,
+    pub score: f64,
+    pub activities: i64,
+}
+
+/// How many activities got each score, by type. Activities without a score
+/// are left out.
+pub async fn get_score_counts(
+    con: &SqlitePool,
+    owner_id: i64,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    filter: &ActivityFilter,
+) -> anyhow::Result<Vec<ScoreCount>> {
+    let types = json_list(&filter.types);
+    let locations = json_list(&filter.locations);
+    let rows = sqlx::query!(
+        r#"
+            select
+                sa.type as "type!: String",
+                t.color as "type_color?: String",
+                sa.score as "score!: f64",
+                count(*) as "activities!: i64"
+            from activities as sa
+            left join activity_types as t on t.owner_id = sa.owner_id and t.name = sa.type
+            where sa.owner_id = ?1 and sa.deleted_at is null and sa.score is not null
+                and (?2 is null or sa.date >= ?2) and (?3 is null or sa.date < ?3)
+                and (?4 is null or sa.type in (select value from json_each(?4)))
+                and (?5 is null or sa.location in (select value from json_each(?5)))
+                and (?6 is null or sa.score >= ?6)
+                and (?7 is null or sa.score <= ?7)
+                and (?8 is null or sa.duration_hours >= ?8)
+                and (?9 is null or sa.duration_hours <= ?9)
+            group by 1, 3
+            order by 1, 3
+        "#,
+        owner_id,
+        from,
+        to,
+        types,
+        locations,
+        filter.min_score,
+        filter.max_score,
+        filter.min_duration,
+        filter.max_duration,
+    )
+    .fetch_all(con)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ScoreCount {
+            type_color: row.type_color.unwrap_or_else(|| string_to_rgb(&row.r#type)),
+            r#type: row.r#type,
+            score: row.score,
+            activities: row.activities,
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+}
+
+#[derive(Debug, FromRow)]
+pub struct UserCredentials {
+    pub id: i64,
+    pub username: String,
+    pub password_hash: String,
+}
+
+/// Creates a user. The first user to register takes ownership of activities
+/// locations and types that were added before there were accounts.
+pub async fn create_user(
+    con: &SqlitePool,
+    username: &str,
+    password_hash: &str,
+    now: NaiveDateTime,
+) -> anyhow::Result<i64> {
+    let mut tx = con.begin().await?;
+    let id = sqlx::query!(
+        "insert into users (username, password_hash, created_at) values (?, ?, ?)",
+        username,
+        password_hash,
+        now,
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+
+    let users = sqlx::query_scalar!(r#"select count(*) as "count!: i64" from users"#)
+        .fetch_one(&mut *tx)
+        .await?;
+    if users == 1 {
+        sqlx::query!(
+            "update activities set owner_id = ? where owner_id is null",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "update locations set owner_id = ? where owner_id is null",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "update activity_types set owner_id = ? where owner_id is null",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(id)
+}
+
+pub async fn get_users(con: &SqlitePool) -> anyhow::Result<Vec<User>> {
+    let users = sqlx::query_as!(
+        User,
+        r#"select id as "id!", username from users order by username"#
+    )
+    .fetch_all(con)
+    .await?;
+    Ok(users)
+}
+
+pub async fn get_user_credentials(
+    con: &SqlitePool,
+    username: &str,
+) -> anyhow::Result<Option<UserCredentials>> {
+    let user = sqlx::query_as!(
+        UserCredentials,
+        "select id as \"id!\", username, password_hash from users where username = ?",
+        username,
+    )
+    .fetch_optional(con)
+    .await?;
+    Ok(user)
+}
+
+pub async fn create_session(
+    con: &SqlitePool,
+    user_id: i64,
+    token: &str,
+    expires_at: NaiveDateTime,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "insert into sessions (token, user_id, expires_at) values (?, ?, ?)",
+        token,
+        user_id,
+        expires_at,
+    )
+    .execute(con)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_session_user(
+    con: &SqlitePool,
+    token: &str,
+    now: NaiveDateTime,
+) -> anyhow::Result<Option<User>> {
+    let user = sqlx::query_as!(
+        User,
+        "select u.id as \"id!\", u.username
+        from sessions as s
+        join users as u on u.id = s.user_id
+        where s.token = ? and s.expires_at > ?",
+        token,
+        now,
+    )
+    .fetch_optional(con)
+    .await?;
+    Ok(user)
+}
+
+pub async fn delete_session(con: &SqlitePool, token: &str) -> anyhow::Result<()> {
+    sqlx::query!("delete from sessions where token = ?", token)
+        .execute(con)
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_expired_sessions(con: &SqlitePool, now: NaiveDateTime) -> anyhow::Result<()> {
+    sqlx::query!("delete from sessions where expires_at <= ?", now)
+        .execute(con)
+        .await?;
+    Ok(())
+}
+
+/// The token of the user's calendar feed, or None if it hasn't been created.
+pub async fn get_feed_token(con: &SqlitePool, user_id: i64) -> anyhow::Result<Option<String>> {
+    let token = sqlx::query_scalar!("select feed_token from users where id = ?", user_id)
+        .fetch_one(con)
+        .await?;
+    Ok(token)
+}
+
+/// Replaces the token of the user's calendar feed, so the old one stops
+/// working.
+pub async fn set_feed_token(con: &SqlitePool, user_id: i64, token: &str) -> anyhow::Result<()> {
+    sqlx::query!(
+        "update users set feed_token = ? where id = ?",
+        token,
+        user_id
+    )
+    .execute(con)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_feed_token_user(con: &SqlitePool, token: &str) -> anyhow::Result<Option<User>> {
+    let user = sqlx::query_as!(
+        User,
+        "select id as \"id!\", username from users where feed_token = ?",
+        token,
+    )
+    .fetch_optional(con)
+    .await?;
+    Ok(user)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::str::FromStr;
+
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+
+    /// A three hour activity without a location, score or description. Tests
+    /// set the fields they need with `..activity(date, type)`.
+    pub(crate) fn activity(date: &str, r#type: &str) -> Activity {
+        Activity {
+            id: None,
+            date: NaiveDateTime::from_str(date).unwrap(),
+            location: "".into(),
+            duration_hours: Some(3.0),
+            r#type: r#type.into(),
+            type_color: None,
+            type_icon: None,
+            score: None,
+            description: "".into(),
+            track: None,
+        }
+    }
+
+    async fn setup() -> SqlitePool {
+        let db_path = "sqlite::memory:";
+        let pool = SqlitePoolOptions::new().connect(db_path).await.unwrap();
+
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        pool
+    }
+
+    async fn add_user(pool: &SqlitePool, username: &str) -> i64 {
+        create_user(pool, username, "hash", NaiveDateTime::default())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[allow(clippy::approx_constant)]
+    async fn insert_and_retrieve() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        let activities = get_activities_from(
+            &pool,
+            owner,
+            Some(NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap()),
+            None,
+            &ActivityFilter::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(0, activities.len());
+
+        let id = insert_activity(
+            &pool,
+            owner,
+            Activity {
+                location: "Norefjell".to_owned(),
+                duration_hours: Some(3.14),
+                score: Some(0.8),
+                description: "This was fun".into(),
+                ..activity("2025-01-01T00:00:00", "Skis")
+            },
+        )
+        .await
+        .unwrap();
+
+        let activities = get_activities_from(
+            &pool,
+            owner,
+            Some(NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap()),
+            None,
+            &ActivityFilter::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, activities.len());
+
+        let saved = activities.into_iter().next().unwrap();
+        assert_eq!(Some(id), saved.id);
+        assert_eq!(Some(1), saved.id);
+        assert_eq!(
+            NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap(),
+            saved.date
+        );
+        assert_eq!(Some(3.14), saved.duration_hours);
+        assert_eq!("Skis".to_owned(), saved.r#type);
+        assert_eq!("Norefjell".to_owned(), saved.location);
+        assert_eq!(Some(0.8), saved.score);
+        assert_eq!("This was fun".to_owned(), saved.description);
+
+        update_activity(
+            &pool,
+            owner,
+            Activity {
+                id: Some(saved.id.unwrap()),
+                location: "Tryvann".to_owned(),
+                duration_hours: Some(56.55),
+                score: Some(1.0),
+                description: "This was OK".into(),
+                ..activity("2025-02-03T04:05:06", "Snowboarding")
+            },
+            NaiveDateTime::default(),
+        )
+        .await
+        .unwrap();
+
+        let activities = get_activities_from(
+            &pool,
+            owner,
+            Some(NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap()),
+            None,
+            &ActivityFilter::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, activities.len());
+
+        let activity = get_activity(&pool, owner, saved.id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(1), activity.id);
+        assert_eq!(
+            NaiveDateTime::from_str("2025-02-03T04:05:06").unwrap(),
+            activity.date
+        );
+        assert_eq!(Some(56.55), activity.duration_hours);
+        assert_eq!("Snowboarding".to_owned(), activity.r#type);
+        assert_eq!("Tryvann", activity.location.as_str());
+        assert_eq!(Some(1.0), activity.score);
+        assert_eq!("This was OK".to_owned(), activity.description);
+
+        let start = NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap();
+        let stopped = NaiveDateTime::from_str("2026-01-01T00:00:00").unwrap();
+        let summary = get_summary(
+            &pool,
+            owner,
+            Some(start),
+            Some(stopped),
+            &ActivityFilter::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, summary[0].days);
+    }
+
+    #[tokio::test]
+    async fn search_follows_inserts_updates_and_deletes() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        let activity = Activity {
+            location: "Hemsedal".to_owned(),
+            description: "Deep <powder> in the trees".into(),
+            ..activity("2025-01-05T10:00:00", "Skis")
+        };
+        let id = insert_activity(&pool, owner, activity.clone())
+            .await
+            .unwrap();
+
+        let results = search_activities(&pool, owner, "powd", 10).await.unwrap();
+        assert_eq!(1, results.len());
+        assert_eq!(Some(id), results[0].activity.id);
+        assert_eq!(
+            "Deep &lt;<mark>powder</mark>&gt; in the trees",
+            results[0].snippet
+        );
+        assert_eq!(
+            1,
+            search_activities(&pool, owner, "hemsedal", 10)
+                .await
+                .unwrap()
+                .len()
+        );
+
+        update_activity(
+            &pool,
+            owner,
+            Activity {
+                id: Some(id),
+                description: "Icy".into(),
+                ..activity
+            },
+            NaiveDateTime::default(),
+        )
+        .await
+        .unwrap();
+        assert!(search_activities(&pool, owner, "powder", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            1,
+            search_activities(&pool, owner, "icy", 10)
+                .await
+                .unwrap()
+                .len()
+        );
+
+        delete_activity(&pool, owner, id, NaiveDateTime::default())
+            .await
+            .unwrap();
+        assert!(search_activities(&pool, owner, "icy", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(search_activities(&pool, owner, "\" AND (", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn activities_are_private_to_their_owner() {
+        let pool = setup().await;
+        let alice = add_user(&pool, "alice").await;
+        let bob = add_user(&pool, "bob").await;
+        let activity = Activity {
+            location: "Hemsedal".to_owned(),
+            description: "Powder".into(),
+            ..activity("2025-01-05T10:00:00", "Skis")
+        };
+        let id = insert_activity(&pool, alice, activity.clone())
+            .await
+            .unwrap();
+
+        assert!(get_activity(&pool, bob, id).await.unwrap().is_none());
+        assert!(
+            get_activities_from(&pool, bob, None, None, &ActivityFilter::default(), None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(search_activities(&pool, bob, "powder", 10)
+            .await
+            .unwrap()
+            .is_empty());
 
-    let summaries = response
-        .into_iter()
-        .map(|x| Summary {
-            type_color: string_to_rgb(&x.r#type),
-            r#type: x.r#type,
-            days: x.days,
-            hours: x.hours,
-        })
-        .collect();
+        let renamed = Activity {
+            id: Some(id),
+            location: "Tryvann".to_owned(),
+            ..activity
+        };
+        update_activity(&pool, bob, renamed, NaiveDateTime::default())
+            .await
+            .unwrap();
+        delete_activity(&pool, bob, id, NaiveDateTime::default())
+            .await
+            .unwrap();
+        let activity = get_activity(&pool, alice, id).await.unwrap().unwrap();
+        assert_eq!("Hemsedal", activity.location);
+    }
 
-    Ok(summaries)
-}
+    #[tokio::test]
+    async fn edits_keep_the_previous_version() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        let now = NaiveDateTime::from_str("2025-01-06T10:00:00").unwrap();
+        let activity = Activity {
+            location: "Hemsedal".to_owned(),
+            description: "Powder".into(),
+            ..activity("2025-01-05T10:00:00", "Skis")
+        };
+        let id = insert_activity(&pool, owner, activity.clone())
+            .await
+            .unwrap();
+        let edited = Activity {
+            id: Some(id),
+            description: "Ice".into(),
+            ..activity
+        };
+        update_activity(&pool, owner, edited.clone(), now)
+            .await
+            .unwrap();
+        update_activity(&pool, owner, edited, now).await.unwrap();
 
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
+        let revisions = get_activity_revisions(&pool, owner, id).await.unwrap();
+        assert_eq!(1, revisions.len());
+        assert_eq!("Powder", revisions[0].activity.description);
+        assert_eq!(Some("alice".to_owned()), revisions[0].edited_by);
+        assert!(revert_activity(&pool, owner + 1, revisions[0].id, now)
+            .await
+            .unwrap()
+            .is_none());
 
-    use sqlx::sqlite::SqlitePoolOptions;
+        assert_eq!(
+            Some(id),
+            revert_activity(&pool, owner, revisions[0].id, now)
+                .await
+                .unwrap()
+        );
+        let activity = get_activity(&pool, owner, id).await.unwrap().unwrap();
+        assert_eq!("Powder", activity.description);
+        let revisions = get_activity_revisions(&pool, owner, id).await.unwrap();
+        assert_eq!(
+            vec!["Ice", "Powder"],
+            revisions
+                .iter()
+                .map(|x| x.activity.description.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
 
-    use super::*;
+    #[tokio::test]
+    async fn deleted_activities_can_be_restored_or_purged() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        let date = NaiveDateTime::from_str("2025-01-05T10:00:00").unwrap();
+        let activity = Activity {
+            location: "Hemsedal".to_owned(),
+            ..activity("2025-01-05T10:00:00", "Skis")
+        };
+        let kept = insert_activity(&pool, owner, activity.clone())
+            .await
+            .unwrap();
+        let purged = insert_activity(&pool, owner, activity).await.unwrap();
+        insert_photo(&pool, owner, purged, "key", "a.jpg", "image/jpeg", date)
+            .await
+            .unwrap();
+        assert_eq!(
+            1,
+            get_photos_of(&pool, owner, &[kept, purged])
+                .await
+                .unwrap()
+                .len()
+        );
+        assert!(get_photos_of(&pool, owner, &[kept])
+            .await
+            .unwrap()
+            .is_empty());
 
-    async fn setup() -> SqlitePool {
-        let db_path = "sqlite::memory:";
-        let pool = SqlitePoolOptions::new().connect(&db_path).await.unwrap();
+        delete_activity(&pool, owner, kept, date).await.unwrap();
+        delete_activity(&pool, owner, purged, date).await.unwrap();
+        assert!(get_activity(&pool, owner, kept).await.unwrap().is_none());
+        assert!(
+            get_activities_from(&pool, owner, None, None, &ActivityFilter::default(), None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(2, get_deleted_activities(&pool, owner).await.unwrap().len());
+        assert!(purge_activity(&pool, owner + 1, purged)
+            .await
+            .unwrap()
+            .is_empty());
 
-        sqlx::migrate!("./migrations")
-            .run(&pool)
+        let location = get_locations(&pool, owner).await.unwrap()[0]
+            .location
+            .id
+            .unwrap();
+        delete_location(&pool, owner, location).await.unwrap();
+        assert!(restore_activity(&pool, owner, kept).await.unwrap());
+        assert!(!restore_activity(&pool, owner, kept).await.unwrap());
+        assert_eq!(
+            vec!["Hemsedal"],
+            get_all_locations(&pool, owner).await.unwrap()
+        );
+
+        let photos = purge_activity(&pool, owner, purged).await.unwrap();
+        assert_eq!(
+            vec!["key"],
+            photos.iter().map(|x| &x.key).collect::<Vec<_>>()
+        );
+        assert!(get_deleted_activities(&pool, owner)
             .await
-            .expect("Failed to run migrations");
+            .unwrap()
+            .is_empty());
+        assert!(get_activity(&pool, owner, kept).await.unwrap().is_some());
+    }
 
-        pool
+    #[tokio::test]
+    async fn first_user_claims_existing_activities() {
+        let pool = setup().await;
+        sqlx::query!(
+            "insert into activities (date, location, duration_hours, type, description)
+            values ('2024-01-01T10:00:00', 'Hemsedal', 2.0, 'Skis', '')"
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let alice = add_user(&pool, "alice").await;
+        let bob = add_user(&pool, "bob").await;
+        let from = NaiveDateTime::default();
+        assert_eq!(
+            1,
+            get_activities_from(
+                &pool,
+                alice,
+                Some(from),
+                None,
+                &ActivityFilter::default(),
+                None
+            )
+            .await
+            .unwrap()
+            .len()
+        );
+        assert!(get_activities_from(
+            &pool,
+            bob,
+            Some(from),
+            None,
+            &ActivityFilter::default(),
+            None
+        )
+        .await
+        .unwrap()
+        .is_empty());
     }
 
     #[tokio::test]
-    async fn insert_and_retrieve() {
+    async fn locations_follow_activities() {
         let pool = setup().await;
-        let activities = get_activities_from(
+        let owner = add_user(&pool, "alice").await;
+        let activity = Activity {
+            location: " Hemsedal".to_owned(),
+            ..activity("2025-01-05T10:00:00", "Skis")
+        };
+        insert_activity(&pool, owner, activity.clone())
+            .await
+            .unwrap();
+        let id = insert_activity(
             &pool,
-            NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap(),
+            owner,
+            Activity {
+                location: "HEMSEDAL".to_owned(),
+                ..activity
+            },
         )
         .await
         .unwrap();
-        assert_eq!(0, activities.len());
 
-        insert_activity(
+        assert_eq!(
+            vec!["Hemsedal".to_owned()],
+            get_all_locations(&pool, owner).await.unwrap()
+        );
+        let activity = get_activity(&pool, owner, id).await.unwrap().unwrap();
+        assert_eq!("Hemsedal", activity.location);
+
+        let usage = get_locations(&pool, owner).await.unwrap();
+        assert_eq!(2, usage[0].activities);
+        let location_id = usage[0].location.id.unwrap();
+        save_location(
+            &pool,
+            owner,
+            Location {
+                name: "Hemsedal Skisenter".to_owned(),
+                elevation_m: Some(1450.0),
+                ..usage[0].location.clone()
+            },
+        )
+        .await
+        .unwrap();
+
+        let activity = get_activity(&pool, owner, id).await.unwrap().unwrap();
+        assert_eq!("Hemsedal Skisenter", activity.location);
+        let location = get_location(&pool, owner, location_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(1450.0), location.elevation_m);
+        assert_eq!(
+            Some(location_id),
+            find_location_id(&pool, owner, "hemsedal skisenter")
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn types_keep_their_colour() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        let activity = activity("2025-01-05T10:00:00", "Skis");
+        insert_activity(&pool, owner, activity.clone())
+            .await
+            .unwrap();
+        let id = insert_activity(
             &pool,
+            owner,
             Activity {
-                id: None,
-                date: NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap(),
-                location: "Norefjell".to_owned(),
-                duration_hours: Some(3.14),
-                r#type: "Skis".into(),
-                type_color: None,
-                score: Some(0.8),
-                description: "This was fun".into(),
+                r#type: "skis ".into(),
+                ..activity
             },
         )
         .await
         .unwrap();
 
-        let activities = get_activities_from(
+        let types = get_activity_types(&pool, owner).await.unwrap();
+        assert_eq!(1, types.len());
+        assert_eq!(2, types[0].activities);
+        assert_eq!(string_to_rgb("Skis"), types[0].activity_type.color);
+
+        save_activity_type(
             &pool,
-            NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap(),
+            owner,
+            ActivityType {
+                name: "Alpine".into(),
+                color: "#123456".into(),
+                icon: "⛷".into(),
+                archived: true,
+                ..types[0].activity_type.clone()
+            },
         )
         .await
         .unwrap();
-        assert_eq!(1, activities.len());
 
-        let activity = activities.into_iter().next().unwrap();
-        assert_eq!(Some(1), activity.id);
+        let activity = get_activity(&pool, owner, id).await.unwrap().unwrap();
+        assert_eq!("Alpine", activity.r#type);
+        assert_eq!(Some("#123456".to_owned()), activity.type_color);
+        assert_eq!(Some("⛷".to_owned()), activity.type_icon);
+        assert!(get_all_types(&pool, owner).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn tracks_replace_each_other() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        let now = NaiveDateTime::from_str("2025-01-05T10:00:00").unwrap();
+        let id = insert_activity(&pool, owner, activity("2025-01-05T10:00:00", "Skis"))
+            .await
+            .unwrap();
+        let stats = TrackStats {
+            distance_km: 12.5,
+            vertical_drop_m: 800.0,
+            max_speed_kmh: 60.0,
+            runs: 3,
+        };
+
+        let first = save_track(&pool, owner, "first.gpx", b"1", now)
+            .await
+            .unwrap();
+        attach_track(&pool, owner, first, id, &stats).await.unwrap();
+        let second = save_track(&pool, owner, "second.gpx", b"2", now)
+            .await
+            .unwrap();
+        attach_track(&pool, owner, second, id, &TrackStats { runs: 4, ..stats })
+            .await
+            .unwrap();
+
+        let activity = get_activity(&pool, owner, id).await.unwrap().unwrap();
+        assert_eq!(Some(4), activity.track.map(|x| x.runs));
+        let track = get_activity_track(&pool, owner, id).await.unwrap().unwrap();
         assert_eq!(
-            NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap(),
-            activity.date
+            ("second.gpx", b"2".to_vec()),
+            (track.filename.as_str(), track.data)
         );
-        assert_eq!(Some(3.14), activity.duration_hours);
-        assert_eq!("Skis".to_owned(), activity.r#type);
-        assert_eq!("Norefjell".to_owned(), activity.location);
-        assert_eq!(Some(0.8), activity.score);
-        assert_eq!("This was fun".to_owned(), activity.description);
+        assert!(get_track(&pool, owner, first).await.unwrap().is_none());
 
-        update_activity(
+        let other = add_user(&pool, "bob").await;
+        assert!(get_activity_track(&pool, other, id)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn one_entry_template_is_the_default() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        let template = EntryTemplate {
+            name: "After work".into(),
+            r#type: "Skis".into(),
+            duration_hours: Some(2.0),
+            is_default: true,
+            ..EntryTemplate::default()
+        };
+        let first = save_entry_template(&pool, owner, template.clone())
+            .await
+            .unwrap();
+        let second = save_entry_template(
             &pool,
-            Activity {
-                id: Some(activity.id.unwrap()),
-                date: NaiveDateTime::from_str("2025-02-03T04:05:06").unwrap(),
-                location: "Tryvann".to_owned(),
-                duration_hours: Some(56.55),
-                r#type: "Snowboarding".to_owned(),
-                type_color: None,
-                score: Some(1.0),
-                description: "This was OK".into(),
+            owner,
+            EntryTemplate {
+                name: "Weekend".into(),
+                ..template
             },
         )
         .await
         .unwrap();
 
+        let default = get_default_entry_template(&pool, owner).await.unwrap();
+        assert_eq!(Some(second), default.and_then(|x| x.id));
+        let first = get_entry_template(&pool, owner, first)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!first.is_default);
+        assert_eq!(
+            Some(first.id.unwrap()),
+            find_entry_template_id(&pool, owner, "after WORK")
+                .await
+                .unwrap()
+        );
+
+        let other = add_user(&pool, "bob").await;
+        assert!(get_entry_templates(&pool, other).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn fallback_colours_are_stable() {
+        assert_eq!("#2F7FF6", string_to_rgb("Skis"));
+    }
+
+    #[tokio::test]
+    async fn aggregate_stats() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        for (date, location, hours, score) in [
+            ("2025-01-04T10:00:00", "Hemsedal", 2.0, Some(0.5)),
+            ("2025-01-04T14:00:00", "Hemsedal", 1.0, None),
+            ("2025-02-01T10:00:00", "", 3.0, Some(1.0)),
+        ] {
+            insert_activity(
+                &pool,
+                owner,
+                Activity {
+                    location: location.to_owned(),
+                    duration_hours: Some(hours),
+                    score,
+                    ..activity(date, "Skis")
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let from = NaiveDateTime::from_str("2024-10-01T00:00:00").unwrap();
+        let to = NaiveDateTime::from_str("2025-10-01T00:00:00").unwrap();
+
+        let months = get_stats_by_month(&pool, owner, from, to).await.unwrap();
+        let months: Vec<_> = months.iter().map(|x| (x.month.as_str(), x.days)).collect();
+        assert_eq!(vec![("2025-01", 1), ("2025-02", 1)], months);
+
+        let weekdays = get_stats_by_weekday(&pool, owner, from, to).await.unwrap();
+        assert_eq!(1, weekdays.len());
+        assert_eq!(
+            (6, 2, 6.0),
+            (weekdays[0].weekday, weekdays[0].days, weekdays[0].hours)
+        );
+
+        let locations = get_stats_by_location(&pool, owner, from, to).await.unwrap();
+        assert_eq!(1, locations.len());
+        assert_eq!(Some(0.5), locations[0].average_score);
+
+        let types = get_stats_by_type(&pool, owner, from, to).await.unwrap();
+        assert_eq!(
+            (3, Some(0.75)),
+            (types[0].activities, types[0].average_score)
+        );
+
+        let totals = get_daily_totals(&pool, owner, None, None, &ActivityFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(2, totals.len());
+        assert_eq!(3.0, totals[0].hours);
+    }
+
+    #[test]
+    fn stored_date_formats() {
+        let expected = NaiveDateTime::from_str("2024-01-02T03:04:05").unwrap();
+        assert_eq!(expected, parse_stored_date("2024-01-02 03:04:05").unwrap());
+        assert_eq!(expected, parse_stored_date("2024-01-02T03:04:05").unwrap());
+        assert!(parse_stored_date("yesterday").is_err());
+    }
+
+    #[tokio::test]
+    async fn activities_in_range() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        for date in [
+            "2024-09-30T10:00:00",
+            "2024-10-01T10:00:00",
+            "2025-10-01T10:00:00",
+        ] {
+            insert_activity(
+                &pool,
+                owner,
+                Activity {
+                    location: "Hemsedal".to_owned(),
+                    duration_hours: Some(2.0),
+                    ..activity(date, "Skis")
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let from = NaiveDateTime::from_str("2024-10-01T00:00:00").unwrap();
+        let to = NaiveDateTime::from_str("2025-10-01T00:00:00").unwrap();
         let activities = get_activities_from(
             &pool,
-            NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap(),
+            owner,
+            Some(from),
+            Some(to),
+            &ActivityFilter::default(),
+            None,
         )
         .await
         .unwrap();
         assert_eq!(1, activities.len());
+        assert_eq!(
+            NaiveDateTime::from_str("2024-10-01T10:00:00").unwrap(),
+            activities[0].date
+        );
+
+        let activities = get_activities_from(
+            &pool,
+            owner,
+            Some(from),
+            None,
+            &ActivityFilter::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(2, activities.len());
+
+        let dates = get_activity_dates(&pool, owner).await.unwrap();
+        assert_eq!(3, dates.len());
+        assert_eq!(NaiveDate::from_ymd_opt(2024, 9, 30).unwrap(), dates[0]);
+    }
 
-        let activity = get_activity(&pool, activity.id.unwrap())
+    #[tokio::test]
+    async fn pages_continue_after_the_cursor() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        for date in [
+            "2025-01-05T10:00:00",
+            "2025-01-05T10:00:00",
+            "2025-01-06T10:00:00",
+            "2025-01-05T09:00:00",
+        ] {
+            insert_activity(
+                &pool,
+                owner,
+                Activity {
+                    duration_hours: None,
+                    ..activity(date, "Skis")
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let mut after = None;
+        let mut ids = vec![];
+        loop {
+            let page = Page { after, limit: 2 };
+            let activities = get_activities_from(
+                &pool,
+                owner,
+                None,
+                None,
+                &ActivityFilter::default(),
+                Some(page),
+            )
+            .await
+            .unwrap();
+            ids.extend(activities.iter().map(|x| x.id.unwrap()));
+            match activities.last() {
+                Some(last) => after = Cursor::of(last),
+                None => break,
+            }
+        }
+        assert_eq!(vec![3, 2, 1, 4], ids);
+    }
+
+    #[tokio::test]
+    async fn migrated_dates_are_stored_like_new_ones() {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let migrator = sqlx::migrate!("./migrations");
+        let mut before = sqlx::migrate!("./migrations");
+        before.migrations = migrator
+            .migrations
+            .iter()
+            .filter(|x| x.version < 15)
+            .cloned()
+            .collect();
+        before.run(&pool).await.unwrap();
+        let owner = add_user(&pool, "alice").await;
+        // Rows converted from epoch seconds use `T` as separator.
+        sqlx::query!(
+            "insert into activities (owner_id, date, location, type, description)
+            values (?, '2025-01-05T09:00:00', '', 'Skis', '')",
+            owner
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        migrator.run(&pool).await.unwrap();
+
+        let date = sqlx::query_scalar!("select date from activities")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!("2025-01-05 09:00:00", date);
+    }
+
+    #[tokio::test]
+    async fn filters_apply_to_activities_and_summary() {
+        let pool = setup().await;
+        let owner = add_user(&pool, "alice").await;
+        for (r#type, location, hours, score) in [
+            ("Skis", "Hemsedal", 2.0, Some(0.9)),
+            ("Skis", "Tryvann", 1.0, Some(0.4)),
+            ("Board", "Hemsedal", 3.0, None),
+        ] {
+            insert_activity(
+                &pool,
+                owner,
+                Activity {
+                    location: location.to_owned(),
+                    duration_hours: Some(hours),
+                    score,
+                    ..activity("2025-01-05T10:00:00", r#type)
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let count = |filter: ActivityFilter| {
+            let pool = pool.clone();
+            async move {
+                let activities = get_activities_from(&pool, owner, None, None, &filter, None)
+                    .await
+                    .unwrap();
+                let summary = get_summary(&pool, owner, None, None, &filter)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    activities.len() as i64,
+                    summary.iter().map(|x| x.days).sum::<i64>()
+                );
+                activities.len()
+            }
+        };
+
+        assert_eq!(3, count(ActivityFilter::default()).await);
+        let skis = ActivityFilter {
+            types: vec!["Skis".into()],
+            ..ActivityFilter::default()
+        };
+        assert_eq!(2, count(skis).await);
+        let places = ActivityFilter {
+            locations: vec!["Hemsedal".into(), "Tryvann".into()],
+            min_duration: Some(2.0),
+            ..ActivityFilter::default()
+        };
+        assert_eq!(2, count(places).await);
+        let scored = ActivityFilter {
+            min_score: Some(0.5),
+            max_score: Some(1.0),
+            ..ActivityFilter::default()
+        };
+        assert_eq!(1, count(scored).await);
+
+        let days = get_daily_totals(&pool, owner, None, None, &ActivityFilter::default())
             .await
-            .unwrap()
             .unwrap();
-        assert_eq!(Some(1), activity.id);
         assert_eq!(
-            NaiveDateTime::from_str("2025-02-03T04:05:06").unwrap(),
-            activity.date
+            vec![DailyTotal {
+                date: NaiveDate::from_ymd_opt(2025, 1, 5).unwrap(),
+                activities: 3,
+                hours: 6.0,
+                average_score: Some(0.65),
+            }],
+            days
         );
-        assert_eq!(Some(56.55), activity.duration_hours);
-        assert_eq!("Snowboarding".to_owned(), activity.r#type);
-        assert_eq!("Tryvann", activity.location.as_str());
-        assert_eq!(Some(1.0), activity.score);
-        assert_eq!("This was OK".to_owned(), activity.description);
 
-        let start = NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap();
-        let stopped = NaiveDateTime::from_str("2026-01-01T00:00:00").unwrap();
-        let summary = get_summary(&pool, start, stopped).await.unwrap();
-        assert_eq!(1, summary[0].days);
+        let all = ActivityFilter::default();
+        let totals = get_type_day_totals(&pool, owner, None, None, &all)
+            .await
+            .unwrap();
+        let totals: Vec<_> = totals
+            .iter()
+            .map(|x| (x.r#type.as_str(), x.activities, x.hours))
+            .collect();
+        assert_eq!(vec![("Board", 1, 3.0), ("Skis", 2, 3.0)], totals);
+        let scores = get_score_counts(&pool, owner, None, None, &all)
+            .await
+            .unwrap();
+        let scores: Vec<_> = scores
+            .iter()
+            .map(|x| (x.r#type.as_str(), x.score, x.activities))
+            .collect();
+        assert_eq!(vec![("Skis", 0.4, 1), ("Skis", 0.9, 1)], scores);
     }
 }
@@ -0,0 +1,118 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use tracing::warn;
+
+use crate::models::Photo;
+
+/// Thumbnails fit within a square of this many pixels.
+const THUMBNAIL_SIZE: u32 = 320;
+
+/// An uploaded image that has been checked and thumbnailed, but not stored
+/// yet.
+pub struct PreparedPhoto {
+    pub filename: String,
+    pub content_type: &'static str,
+    pub data: Vec<u8>,
+    pub thumbnail: Vec<u8>,
+}
+
+/// Decodes the upload and creates a JPEG thumbnail, turned the way the camera
+/// says it was held.
+pub fn prepare_photo(filename: &str, data: Vec<u8>) -> Result<PreparedPhoto> {
+    let reader = ImageReader::new(Cursor::new(&data))
+        .with_guessed_format()
+        .context("Failed to read the image")?;
+    let content_type = match reader.format() {
+        Some(ImageFormat::Jpeg) => "image/jpeg",
+        Some(ImageFormat::Png) => "image/png",
+        Some(ImageFormat::WebP) => "image/webp",
+        _ => anyhow::bail!("Only JPEG, PNG and WebP images are supported"),
+    };
+    let mut decoder = reader.into_decoder().context("Not a valid image")?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder).context("Not a valid image")?;
+    image.apply_orientation(orientation);
+
+    let mut thumbnail = vec![];
+    DynamicImage::ImageRgb8(image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8())
+        .write_to(&mut Cursor::new(&mut thumbnail), ImageFormat::Jpeg)?;
+
+    Ok(PreparedPhoto {
+        filename: filename.to_owned(),
+        content_type,
+        data,
+        thumbnail,
+    })
+}
+
+/// Photos and their thumbnails on disk, named after the key stored in the
+/// database.
+#[derive(Debug, Clone)]
+pub struct PhotoStore {
+    dir: PathBuf,
+}
+
+impl PhotoStore {
+    pub fn new(data_dir: &Path) -> Result<Self> {
+        let dir = data_dir.join("photos");
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        Ok(PhotoStore { dir })
+    }
+
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+
+    pub fn thumbnail_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.thumb.jpg", key))
+    }
+
+    /// Writes the photo and returns the key it was stored under.
+    pub async fn save(&self, photo: &PreparedPhoto) -> Result<String> {
+        let key = uuid::Uuid::new_v4().to_string();
+        tokio::fs::write(self.path(&key), &photo.data).await?;
+        tokio::fs::write(self.thumbnail_path(&key), &photo.thumbnail).await?;
+        Ok(key)
+    }
+
+    /// Removes the files of photos that are already gone from the database.
+    /// Failures are only logged, as the rows are what the app goes by.
+    pub async fn remove(&self, photos: &[Photo]) {
+        for photo in photos {
+            for path in [self.path(&photo.key), self.thumbnail_path(&photo.key)] {
+                if let Err(e) = tokio::fs::remove_file(&path).await {
+                    warn!("Failed to remove {:?}: {}", path, e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn thumbnails_are_small_jpegs() {
+        let mut png = vec![];
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(1000, 500, Rgb([200, 10, 10])))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let photo = prepare_photo("red.png", png).unwrap();
+        assert_eq!("image/png", photo.content_type);
+        let thumbnail = image::load_from_memory(&photo.thumbnail).unwrap();
+        assert_eq!((320, 160), (thumbnail.width(), thumbnail.height()));
+        assert_eq!(
+            Some(ImageFormat::Jpeg),
+            image::guess_format(&photo.thumbnail).ok()
+        );
+
+        assert!(prepare_photo("notes.txt", b"hello".to_vec()).is_err());
+    }
+}
@@ -1,38 +1,403 @@
-use axum::extract::{Path, Query, State};
-use axum::response::{Html, Redirect};
+use std::collections::BTreeMap;
+
+use axum::extract::multipart::MultipartError;
+use axum::extract::rejection::FormRejection;
+use axum::extract::{Multipart, Path, Query, RawQuery, State};
+use axum::http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, HOST};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum::Form;
-use chrono::{Datelike, Months, NaiveDate, NaiveDateTime, NaiveTime, SubsecRound, Utc};
-use chrono_tz::Europe::Oslo;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use minijinja::context;
+use serde::de::DeserializeOwned;
 
+use crate::auth::new_token;
+use crate::calendar::CalendarMonth;
+use crate::charts::{
+    cumulative_hours, days_per_month, heatmap, score_distribution, HeatmapRange, HeatmapValue,
+};
+use crate::error::{AppError, AppResult};
+use crate::history::describe_revisions;
+use crate::ical::write_calendar;
+use crate::import_export::{preview_import, run_import, write_csv};
 use crate::models::{
-    delete_activity, get_activities_from, get_activity, get_all_locations, get_all_types,
-    get_summary, insert_activity, update_activity, Activity,
+    attach_track, delete_activity, delete_activity_type, delete_entry_template, delete_location,
+    delete_photo, empty_trash, find_activity_type_id, find_entry_template_id, find_location_id,
+    get_activities_from, get_activity, get_activity_dates, get_activity_photos,
+    get_activity_revisions, get_activity_track, get_activity_type, get_activity_types,
+    get_all_locations, get_all_types, get_daily_totals, get_default_entry_template,
+    get_deleted_activities, get_entry_template, get_entry_templates, get_feed_token,
+    get_feed_token_user, get_location, get_locations, get_photo, get_photos_of, get_score_counts,
+    get_stats_by_location, get_stats_by_month, get_stats_by_type, get_stats_by_weekday,
+    get_summary, get_track, get_type_day_totals, insert_activity, insert_photo, purge_activity,
+    restore_activity, revert_activity, save_activity_type, save_entry_template, save_location,
+    save_track, search_activities, set_feed_token, update_activity, Activity, ActivityFilter,
+    ActivityType, Cursor, EntryTemplate, Location, Page, Photo, TrackStats, User,
+};
+use crate::photos::{prepare_photo, PreparedPhoto};
+use crate::season::{seasons_for_dates, Season};
+use crate::stats::{longest_streaks, month_label, season_totals, weekdays};
+use crate::tracks::{parse_track, summarize, TrackSummary};
+use crate::validation::{
+    ActivityForm, ActivityTypeForm, EntryTemplateForm, FieldErrors, Filter, FilterForm,
+    LocationForm,
 };
 use crate::AppState;
 
-pub async fn get_index(State(state): State<AppState>) -> Html<String> {
-    let now = Utc::now();
-    let started = if now.month() >= 10 {
-        NaiveDateTime::new(
-            NaiveDate::from_ymd_opt(now.year(), 10, 1).unwrap(),
-            NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-        )
+#[derive(serde::Deserialize)]
+pub struct SeasonQuery {
+    /// Set after a deletion, to offer undoing it.
+    deleted: Option<i64>,
+    /// Where to continue the list when loading more, see [`Cursor`].
+    after_date: Option<NaiveDateTime>,
+    after_id: Option<i64>,
+    #[serde(default)]
+    heatmap: HeatmapValue,
+    #[serde(default)]
+    heatmap_range: HeatmapRange,
+}
+
+impl SeasonQuery {
+    fn after(&self) -> Option<Cursor> {
+        Some(Cursor {
+            date: self.after_date?,
+            id: self.after_id?,
+        })
+    }
+}
+
+/// Parses the query string. Unlike [`Query`] this allows repeated keys, which
+/// the filter uses for types and locations.
+fn parse_query<T: DeserializeOwned>(query: Option<&str>) -> AppResult<T> {
+    serde_html_form::from_str(query.unwrap_or_default())
+        .map_err(|e| AppError::BadRequest(format!("Invalid query: {}", e)))
+}
+
+const PAGE_SIZE: usize = 50;
+
+/// One page of activities, and where the next page starts if there is one.
+async fn get_page(
+    state: &AppState,
+    user: &User,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    filter: &ActivityFilter,
+    after: Option<Cursor>,
+) -> AppResult<(Vec<Activity>, Option<Cursor>)> {
+    let page = Page {
+        after,
+        limit: PAGE_SIZE as i64 + 1,
+    };
+    let mut activities =
+        get_activities_from(&state.pool, user.id, from, to, filter, Some(page)).await?;
+    let more = if activities.len() > PAGE_SIZE {
+        activities.truncate(PAGE_SIZE);
+        activities.last().and_then(Cursor::of)
     } else {
-        NaiveDateTime::new(
-            NaiveDate::from_ymd_opt(now.year() - 1, 10, 1).unwrap(),
-            NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+        None
+    };
+    Ok((activities, more))
+}
+
+/// The photos of the activities, by activity id.
+async fn get_photos_by_activity(
+    state: &AppState,
+    user: &User,
+    activities: &[Activity],
+) -> AppResult<BTreeMap<i64, Vec<Photo>>> {
+    let ids: Vec<i64> = activities.iter().filter_map(|x| x.id).collect();
+    let mut photos: BTreeMap<i64, Vec<Photo>> = BTreeMap::new();
+    for photo in get_photos_of(&state.pool, user.id, &ids).await? {
+        photos.entry(photo.activity_id).or_default().push(photo);
+    }
+    Ok(photos)
+}
+
+pub async fn get_index(
+    State(state): State<AppState>,
+    user: User,
+    RawQuery(query): RawQuery,
+) -> AppResult<Html<String>> {
+    let season = state.settings.current_season();
+    render_season(&state, &user, season, query.as_deref()).await
+}
+
+pub async fn get_season(
+    State(state): State<AppState>,
+    user: User,
+    Path(year): Path<i32>,
+    RawQuery(query): RawQuery,
+) -> AppResult<Html<String>> {
+    let season = Season::new(year, state.settings.season_start)
+        .ok_or_else(|| AppError::NotFound(format!("There is no season {}", year)))?;
+    render_season(&state, &user, season, query.as_deref()).await
+}
+
+async fn render_season(
+    state: &AppState,
+    user: &User,
+    season: Season,
+    query: Option<&str>,
+) -> AppResult<Html<String>> {
+    tracing::info!("Started: {:?}, ended: {:?}", season.start, season.end);
+    let list: SeasonQuery = parse_query(query)?;
+    let filter_form: FilterForm = parse_query(query)?;
+    // An invalid filter is shown with its errors, and the list is left unfiltered.
+    let (filter, filter_errors) = match filter_form.parse() {
+        Ok(filter) => (filter, FieldErrors::default()),
+        Err(errors) => (Filter::default(), errors),
+    };
+    let (from, to) = if filter.has_dates() {
+        filter.range()
+    } else {
+        (Some(season.start), Some(season.end))
+    };
+
+    let after = list.after();
+    let (activities, more) = get_page(state, user, from, to, &filter.activities, after).await?;
+    let summaries = get_summary(&state.pool, user.id, from, to, &filter.activities).await?;
+    let photos = get_photos_by_activity(state, user, &activities).await?;
+
+    let current = state.settings.current_season();
+    let mut seasons = seasons_for_dates(
+        get_activity_dates(&state.pool, user.id).await?,
+        state.settings.season_start,
+    );
+    if !seasons.contains(&current) {
+        seasons.insert(0, current);
+    }
+    let previous = season.previous();
+    let next = season.next().filter(|next| next.year <= current.year);
+    let deleted = match list.deleted {
+        Some(id) => get_deleted_activities(&state.pool, user.id)
+            .await?
+            .into_iter()
+            .find(|x| x.activity.id == Some(id)),
+        None => None,
+    };
+
+    // The heatmap always covers the season or its year, on every page. Days
+    // outside the date filter are left empty.
+    let (start, end) = list.heatmap_range.dates(&season);
+    let (mut heatmap_from, mut heatmap_to) =
+        (start.and_time(NaiveTime::MIN), end.and_time(NaiveTime::MIN));
+    if filter.has_dates() {
+        let (from, to) = filter.range();
+        heatmap_from = from.map_or(heatmap_from, |from| heatmap_from.max(from));
+        heatmap_to = to.map_or(heatmap_to, |to| heatmap_to.min(to));
+    }
+    let days = get_daily_totals(
+        &state.pool,
+        user.id,
+        Some(heatmap_from),
+        Some(heatmap_to),
+        &filter.activities,
+    )
+    .await?;
+    let heatmap = (!days.is_empty() || !summaries.is_empty()).then(|| {
+        context!(
+            svg => minijinja::Value::from_safe_string(heatmap(&days, start, end, list.heatmap)),
+            value => list.heatmap,
+            range => list.heatmap_range,
+            has_scores => days.iter().any(|x| x.average_score.is_some()),
         )
+    });
+
+    // The other charts cover the whole season, and are only needed on the first
+    // page. They are left out for other date ranges.
+    let charts = if after.is_none() && !filter.has_dates() && !activities.is_empty() {
+        let filter = &filter.activities;
+        let totals = get_type_day_totals(&state.pool, user.id, from, to, filter).await?;
+        let scores = get_score_counts(&state.pool, user.id, from, to, filter).await?;
+        let has_scores = !scores.is_empty();
+        Some(context!(
+            hours => minijinja::Value::from_safe_string(cumulative_hours(&totals, &season)),
+            days => minijinja::Value::from_safe_string(days_per_month(&totals, &season)),
+            scores => has_scores.then(|| {
+                minijinja::Value::from_safe_string(score_distribution(&scores))
+            }),
+        ))
+    } else {
+        None
     };
-    let ended = started.checked_add_months(Months::new(12)).unwrap();
-    tracing::info!("Started: {:?}, ended: {:?}", started, ended);
-    let activities = get_activities_from(&state.pool, started).await.unwrap();
-    let summaries = get_summary(&state.pool, started, ended).await.unwrap();
 
-    state.render(
+    Ok(state.render(
         "index.html",
-        context!(activities => activities, summaries => summaries,),
+        context!(
+            user => user,
+            activities => activities,
+            more => more,
+            photos => photos,
+            summaries => summaries,
+            heatmap => heatmap,
+            charts => charts,
+            filter => filter_form,
+            filter_errors => filter_errors,
+            filter_query => filter_form.query_string(),
+            filtered => !filter_form.is_empty(),
+            date_range => filter.has_dates(),
+            types => get_all_types(&state.pool, user.id).await?,
+            locations => get_all_locations(&state.pool, user.id).await?,
+            season => season,
+            previous => previous,
+            next => next,
+            seasons => seasons,
+            deleted => deleted,
+        ),
+    ))
+}
+
+pub async fn get_all(
+    State(state): State<AppState>,
+    user: User,
+    Query(query): Query<SeasonQuery>,
+) -> AppResult<Html<String>> {
+    let (activities, more) = get_page(
+        &state,
+        &user,
+        None,
+        None,
+        &ActivityFilter::default(),
+        query.after(),
     )
+    .await?;
+    let photos = get_photos_by_activity(&state, &user, &activities).await?;
+
+    Ok(state.render(
+        "all.html",
+        context!(
+            user => user,
+            activities => activities,
+            more => more,
+            photos => photos,
+        ),
+    ))
+}
+
+pub async fn get_calendar(State(state): State<AppState>, _user: User) -> Redirect {
+    let today = state.settings.local_now().date();
+    Redirect::to(&format!("/calendar/{}/{}", today.year(), today.month()))
+}
+
+pub async fn get_calendar_month(
+    State(state): State<AppState>,
+    user: User,
+    Path((year, month)): Path<(i32, u32)>,
+) -> AppResult<Html<String>> {
+    let (start, end) = CalendarMonth::range(year, month)
+        .ok_or_else(|| AppError::NotFound(format!("There is no month {}-{}", year, month)))?;
+    let activities = get_activities_from(
+        &state.pool,
+        user.id,
+        Some(start.and_time(NaiveTime::MIN)),
+        Some(end.and_time(NaiveTime::MIN)),
+        &ActivityFilter::default(),
+        None,
+    )
+    .await?;
+    let today = state.settings.local_now().date();
+    let calendar = CalendarMonth::new(year, month, activities, today)
+        .ok_or_else(|| AppError::NotFound(format!("There is no month {}-{}", year, month)))?;
+
+    Ok(state.render(
+        "calendar.html",
+        context!(user => user, calendar => calendar),
+    ))
+}
+
+const STREAK_LIMIT: usize = 5;
+
+pub async fn get_stats(State(state): State<AppState>, user: User) -> AppResult<Html<String>> {
+    let season = state.settings.current_season();
+    render_stats(&state, &user, season).await
+}
+
+pub async fn get_season_stats(
+    State(state): State<AppState>,
+    user: User,
+    Path(year): Path<i32>,
+) -> AppResult<Html<String>> {
+    let season = Season::new(year, state.settings.season_start)
+        .ok_or_else(|| AppError::NotFound(format!("There is no season {}", year)))?;
+    render_stats(&state, &user, season).await
+}
+
+async fn render_stats(state: &AppState, user: &User, season: Season) -> AppResult<Html<String>> {
+    let (from, to) = (season.start, season.end);
+    let months: Vec<_> = get_stats_by_month(&state.pool, user.id, from, to)
+        .await?
+        .into_iter()
+        .map(|x| context!(label => month_label(&x.month), days => x.days, hours => x.hours))
+        .collect();
+    let weekdays = weekdays(&get_stats_by_weekday(&state.pool, user.id, from, to).await?);
+    let types = get_stats_by_type(&state.pool, user.id, from, to).await?;
+    let locations = get_stats_by_location(&state.pool, user.id, from, to).await?;
+
+    let totals =
+        get_daily_totals(&state.pool, user.id, None, None, &ActivityFilter::default()).await?;
+    let seasons = season_totals(&totals, state.settings.season_start);
+    let dates: Vec<_> = totals.iter().map(|x| x.date).collect();
+    let streaks = longest_streaks(&dates, STREAK_LIMIT);
+    let season_dates: Vec<_> = dates
+        .iter()
+        .copied()
+        .filter(|date| Season::containing(*date, state.settings.season_start) == season)
+        .collect();
+    let season_streak = longest_streaks(&season_dates, 1).into_iter().next();
+
+    let current = state.settings.current_season();
+    let previous = season.previous();
+    let next = season.next().filter(|next| next.year <= current.year);
+
+    Ok(state.render(
+        "stats.html",
+        context!(
+            user => user,
+            season => season,
+            previous => previous,
+            next => next,
+            months => months,
+            weekdays => weekdays,
+            types => types,
+            locations => locations,
+            seasons => seasons,
+            streaks => streaks,
+            season_streak => season_streak,
+        ),
+    ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+const SEARCH_LIMIT: i64 = 100;
+
+pub async fn get_search(
+    State(state): State<AppState>,
+    user: User,
+    Query(query): Query<SearchQuery>,
+) -> AppResult<Html<String>> {
+    let results = search_activities(&state.pool, user.id, &query.q, SEARCH_LIMIT).await?;
+    let results: Vec<_> = results
+        .into_iter()
+        .map(|result| {
+            let season =
+                Season::containing(result.activity.date.date(), state.settings.season_start);
+            context!(
+                activity => result.activity,
+                snippet => minijinja::Value::from_safe_string(result.snippet),
+                season => season,
+            )
+        })
+        .collect();
+
+    Ok(state.render(
+        "search.html",
+        context!(user => user, q => query.q, results => results),
+    ))
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -40,58 +405,1157 @@ pub struct AddFormStruct {
     id: Option<i64>,
 }
 
+#[derive(serde::Deserialize)]
+pub struct AddActivityQuery {
+    id: Option<i64>,
+    /// Start from a copy of this activity.
+    duplicate: Option<i64>,
+    /// Start from this entry template instead of the default one.
+    template: Option<i64>,
+    /// Put the new activity on this day instead of today.
+    date: Option<NaiveDate>,
+}
+
 pub async fn get_add(
-    Query(q): Query<AddFormStruct>,
+    Query(q): Query<AddActivityQuery>,
     State(state): State<AppState>,
-) -> Html<String> {
-    let activity_types = get_all_types(&state.pool, None, None).await.unwrap();
-    let locations = get_all_locations(&state.pool, None, None).await.unwrap();
-
+    user: User,
+) -> AppResult<Html<String>> {
+    let now = state.settings.local_now();
+    let day = q.date.unwrap_or(now.date());
     let activity = if let Some(id) = q.id {
-        get_activity(&state.pool, id).await.unwrap().unwrap()
-    } else {
-        let date = Utc::now()
-            .with_timezone(&Oslo)
-            .naive_local()
-            .trunc_subsecs(0);
-
+        find_activity(&state, &user, id).await?
+    } else if let Some(id) = q.duplicate {
+        // Same time of day, but today. Tracks and photos belong to the
+        // original.
+        let original = find_activity(&state, &user, id).await?;
         Activity {
             id: None,
-            date,
-            duration_hours: None,
-            location: "".to_owned(),
-            r#type: activity_types[0].clone(),
-            type_color: None,
-            score: None,
-            description: "".to_owned(),
+            date: day.and_time(original.date.time()),
+            track: None,
+            ..original
+        }
+    } else {
+        let template = match q.template {
+            Some(id) => Some(find_entry_template(&state, &user, id).await?),
+            None => get_default_entry_template(&state.pool, user.id).await?,
+        };
+        let mut activity = template
+            .unwrap_or_default()
+            .activity(day.and_time(now.time()));
+        if activity.r#type.is_empty() {
+            activity.r#type = get_all_types(&state.pool, user.id)
+                .await?
+                .into_iter()
+                .next()
+                .unwrap_or_default();
         }
+        activity
     };
 
-    state.render(
+    render_edit(
+        &state,
+        &user,
+        ActivityForm::from(&activity),
+        activity.track.as_ref(),
+        FieldErrors::default(),
+    )
+    .await
+}
+
+async fn render_edit(
+    state: &AppState,
+    user: &User,
+    form: ActivityForm,
+    track: Option<&TrackStats>,
+    errors: FieldErrors,
+) -> AppResult<Html<String>> {
+    let activity_types = get_all_types(&state.pool, user.id).await?;
+    let locations = get_all_locations(&state.pool, user.id).await?;
+    let photos = match form.id {
+        Some(id) => get_activity_photos(&state.pool, user.id, id).await?,
+        None => vec![],
+    };
+    let entry_templates = get_entry_templates(&state.pool, user.id).await?;
+
+    Ok(state.render(
         "edit.html",
         context!(
+            user => user,
+            activity => form,
+            track => track,
+            photos => photos,
+            entry_templates => entry_templates,
+            errors => errors,
+            activity_types => activity_types,
+            locations => locations,
+        ),
+    ))
+}
+
+async fn find_activity(state: &AppState, user: &User, id: i64) -> AppResult<Activity> {
+    get_activity(&state.pool, user.id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Activity {} does not exist", id)))
+}
+
+fn parse_id(value: &str) -> AppResult<Option<i64>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| AppError::BadRequest(format!("'{}' is not a valid id", value)))
+}
+
+/// An uploaded file.
+struct Upload {
+    filename: String,
+    data: Vec<u8>,
+}
+
+/// Files sent along with the edit form.
+#[derive(Default)]
+struct Uploads {
+    track: Option<Upload>,
+    photos: Vec<Upload>,
+    /// Set when the form is larger than the upload limit. The files are then
+    /// left out, along with any fields after them.
+    too_large: bool,
+}
+
+fn is_too_large(error: &MultipartError) -> bool {
+    error.status() == StatusCode::PAYLOAD_TOO_LARGE
+}
+
+/// Reads the edit form, which is sent as multipart so that it can carry a
+/// track file and photos. The text fields come before the files in the form,
+/// so they are kept when the files are too large.
+async fn read_activity_form(multipart: &mut Multipart) -> AppResult<(ActivityForm, Uploads)> {
+    let mut form = ActivityForm::default();
+    let mut uploads = Uploads::default();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) if is_too_large(&e) => {
+                uploads.too_large = true;
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let name = field.name().unwrap_or_default().to_owned();
+        if name == "track" || name == "photos" {
+            let filename = field.file_name().unwrap_or(&name).to_owned();
+            let data = match field.bytes().await {
+                Ok(data) => data,
+                Err(e) if is_too_large(&e) => {
+                    uploads.too_large = true;
+                    break;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            // Browsers send an empty part when no file was chosen.
+            if data.is_empty() {
+                continue;
+            }
+            let upload = Upload {
+                filename,
+                data: data.to_vec(),
+            };
+            if name == "track" {
+                uploads.track = Some(upload);
+            } else {
+                uploads.photos.push(upload);
+            }
+            continue;
+        }
+
+        let value = field.text().await?;
+        match name.as_str() {
+            "id" => form.id = parse_id(&value)?,
+            "track_id" => form.track_id = parse_id(&value)?,
+            "date" => form.date = value,
+            "location" => form.location = value,
+            "duration_hours" => form.duration_hours = value,
+            "type" => form.r#type = value,
+            "score" => form.score = value,
+            "description" => form.description = value,
+            _ => {}
+        }
+    }
+    Ok((form, uploads))
+}
+
+/// Checks the uploaded photos and makes their thumbnails off the async
+/// runtime.
+async fn prepare_photos(uploads: Vec<Upload>) -> AppResult<Result<Vec<PreparedPhoto>, String>> {
+    let prepared = tokio::task::spawn_blocking(move || {
+        uploads
+            .into_iter()
+            .map(|x| {
+                prepare_photo(&x.filename, x.data).map_err(|e| format!("{}: {:#}", x.filename, e))
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.into()))?;
+    Ok(prepared)
+}
+
+pub async fn post_edit(
+    State(state): State<AppState>,
+    user: User,
+    mut multipart: Multipart,
+) -> AppResult<Response> {
+    let (mut form, uploads) = read_activity_form(&mut multipart).await?;
+    let now = state.settings.local_now();
+
+    let mut track: Option<TrackSummary> = None;
+    let mut track_error = None;
+    if let Some(upload) = uploads.track {
+        match parse_track(&upload.data) {
+            Ok(points) => {
+                let id =
+                    save_track(&state.pool, user.id, &upload.filename, &upload.data, now).await?;
+                form.track_id = Some(id);
+                track = Some(summarize(&points));
+            }
+            Err(e) => track_error = Some(format!("{:#}", e)),
+        }
+    } else if let Some(id) = form.track_id {
+        let stored = get_track(&state.pool, user.id, id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Track {} does not exist", id)))?;
+        track = Some(summarize(&parse_track(&stored.data)?));
+    }
+
+    if form.duration_hours.trim().is_empty() {
+        if let Some(hours) = track.as_ref().and_then(|x| x.duration_hours) {
+            form.duration_hours = format!("{:.2}", hours);
+        }
+    }
+
+    let photos = if uploads.too_large {
+        Err(format!(
+            "The files can't be more than {} MB together",
+            state.settings.max_upload_mb
+        ))
+    } else {
+        prepare_photos(uploads.photos).await?
+    };
+
+    let activity = match (form.parse(now), track_error, photos) {
+        (Ok(activity), None, Ok(photos)) => (activity, photos),
+        (result, track_error, photos) => {
+            let mut errors = result.err().unwrap_or_default();
+            if let Some(message) = track_error {
+                errors.add("track", message);
+            }
+            if let Err(message) = photos {
+                errors.add("photos", message);
+            }
+            let stats = track.as_ref().map(|x| &x.stats);
+            let html = render_edit(&state, &user, form, stats, errors).await?;
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, html).into_response());
+        }
+    };
+
+    let (activity, photos) = activity;
+    let id = match activity.id {
+        None => insert_activity(&state.pool, user.id, activity).await?,
+        Some(id) => {
+            find_activity(&state, &user, id).await?;
+            update_activity(&state.pool, user.id, activity, now).await?;
+            id
+        }
+    };
+    if let (Some(track_id), Some(track)) = (form.track_id, &track) {
+        attach_track(&state.pool, user.id, track_id, id, &track.stats).await?;
+    }
+    for photo in photos {
+        let key = state.photos.save(&photo).await?;
+        insert_photo(
+            &state.pool,
+            user.id,
+            id,
+            &key,
+            &photo.filename,
+            photo.content_type,
+            now,
+        )
+        .await?;
+    }
+
+    match form.id {
+        None => Ok(Redirect::to("/").into_response()),
+        Some(id) => Ok(Redirect::to(&format!("/#{}", id)).into_response()),
+    }
+}
+
+pub async fn get_track_file(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    let track = get_activity_track(&state.pool, user.id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Activity {} has no track", id)))?;
+    let filename = track.filename.replace(['"', '\\'], "_");
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/octet-stream".to_owned()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        track.data,
+    )
+        .into_response())
+}
+
+pub async fn post_delete(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Redirect> {
+    delete_activity(&state.pool, user.id, id, state.settings.local_now()).await?;
+
+    Ok(Redirect::to(&format!("/?deleted={}", id)))
+}
+
+pub async fn post_restore(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Redirect> {
+    if !restore_activity(&state.pool, user.id, id).await? {
+        return Err(AppError::NotFound(format!(
+            "Activity {} is not in the trash",
+            id
+        )));
+    }
+    let activity = get_activity(&state.pool, user.id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Activity {} does not exist", id)))?;
+    let season = Season::containing(activity.date.date(), state.settings.season_start);
+
+    Ok(Redirect::to(&format!("/season/{}#{}", season.year, id)))
+}
+
+pub async fn get_history(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Html<String>> {
+    let activity = find_activity(&state, &user, id).await?;
+    let revisions = get_activity_revisions(&state.pool, user.id, id).await?;
+    let season = Season::containing(activity.date.date(), state.settings.season_start);
+    Ok(state.render(
+        "history.html",
+        context!(
+            user => user,
             activity => activity,
+            season => season,
+            revisions => describe_revisions(&activity, &revisions),
+        ),
+    ))
+}
+
+pub async fn post_revert(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Redirect> {
+    let activity_id = revert_activity(&state.pool, user.id, id, state.settings.local_now())
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Revision {} does not exist", id)))?;
+
+    Ok(Redirect::to(&format!("/activity/{}/history", activity_id)))
+}
+
+pub async fn get_trash(State(state): State<AppState>, user: User) -> AppResult<Html<String>> {
+    let activities = get_deleted_activities(&state.pool, user.id).await?;
+    Ok(state.render(
+        "trash.html",
+        context!(user => user, activities => activities),
+    ))
+}
+
+pub async fn post_purge(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Redirect> {
+    let photos = purge_activity(&state.pool, user.id, id).await?;
+    state.photos.remove(&photos).await;
+
+    Ok(Redirect::to("/trash"))
+}
+
+pub async fn post_empty_trash(State(state): State<AppState>, user: User) -> AppResult<Redirect> {
+    let photos = empty_trash(&state.pool, user.id).await?;
+    state.photos.remove(&photos).await;
+
+    Ok(Redirect::to("/trash"))
+}
+
+async fn find_photo(state: &AppState, user: &User, id: i64) -> AppResult<Photo> {
+    get_photo(&state.pool, user.id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Photo {} does not exist", id)))
+}
+
+fn photo_response(content_type: String, data: Vec<u8>) -> Response {
+    (
+        [
+            (CONTENT_TYPE, content_type),
+            // Photos are never changed, only removed.
+            (CACHE_CONTROL, "private, max-age=31536000".to_owned()),
+        ],
+        data,
+    )
+        .into_response()
+}
+
+pub async fn get_photo_file(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    let photo = find_photo(&state, &user, id).await?;
+    let data = tokio::fs::read(state.photos.path(&photo.key))
+        .await
+        .map_err(|e| AppError::Internal(e.into()))?;
+    Ok(photo_response(photo.content_type, data))
+}
+
+pub async fn get_photo_thumbnail(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    let photo = find_photo(&state, &user, id).await?;
+    let data = tokio::fs::read(state.photos.thumbnail_path(&photo.key))
+        .await
+        .map_err(|e| AppError::Internal(e.into()))?;
+    Ok(photo_response("image/jpeg".to_owned(), data))
+}
+
+pub async fn post_delete_photo(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Redirect> {
+    let photo = delete_photo(&state.pool, user.id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Photo {} does not exist", id)))?;
+    state.photos.remove(std::slice::from_ref(&photo)).await;
+
+    Ok(Redirect::to(&format!("/edit?id={}", photo.activity_id)))
+}
+
+pub async fn get_locations_page(
+    State(state): State<AppState>,
+    user: User,
+) -> AppResult<Html<String>> {
+    let locations = get_locations(&state.pool, user.id).await?;
+    Ok(state.render(
+        "locations.html",
+        context!(user => user, locations => locations),
+    ))
+}
+
+pub async fn get_edit_location(
+    Query(q): Query<AddFormStruct>,
+    State(state): State<AppState>,
+    user: User,
+) -> AppResult<Html<String>> {
+    let form = match q.id {
+        Some(id) => LocationForm::from(&find_location(&state, &user, id).await?),
+        None => LocationForm::default(),
+    };
+    Ok(render_edit_location(
+        &state,
+        &user,
+        form,
+        FieldErrors::default(),
+    ))
+}
+
+fn render_edit_location(
+    state: &AppState,
+    user: &User,
+    form: LocationForm,
+    errors: FieldErrors,
+) -> Html<String> {
+    state.render(
+        "edit_location.html",
+        context!(user => user, location => form, errors => errors),
+    )
+}
+
+async fn find_location(state: &AppState, user: &User, id: i64) -> AppResult<Location> {
+    get_location(&state.pool, user.id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Location {} does not exist", id)))
+}
+
+pub async fn post_edit_location(
+    State(state): State<AppState>,
+    user: User,
+    form: Result<Form<LocationForm>, FormRejection>,
+) -> AppResult<Response> {
+    let Form(form) = form?;
+    let location = match form.parse() {
+        Ok(location) => location,
+        Err(errors) => {
+            let html = render_edit_location(&state, &user, form, errors);
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, html).into_response());
+        }
+    };
+
+    let existing = find_location_id(&state.pool, user.id, &location.name).await?;
+    if existing.is_some() && existing != location.id {
+        let mut errors = FieldErrors::default();
+        errors.add("name", "A location with that name already exists");
+        let html = render_edit_location(&state, &user, form, errors);
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, html).into_response());
+    }
+    if let Some(id) = location.id {
+        find_location(&state, &user, id).await?;
+    }
+    save_location(&state.pool, user.id, location).await?;
+    Ok(Redirect::to("/locations").into_response())
+}
+
+pub async fn post_delete_location(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Redirect> {
+    let locations = get_locations(&state.pool, user.id).await?;
+    let usage = locations
+        .iter()
+        .find(|x| x.location.id == Some(id))
+        .ok_or_else(|| AppError::NotFound(format!("Location {} does not exist", id)))?;
+    if usage.activities > 0 {
+        return Err(AppError::BadRequest(format!(
+            "{} is used by {} activities and can't be deleted",
+            usage.location.name, usage.activities
+        )));
+    }
+    delete_location(&state.pool, user.id, id).await?;
+
+    Ok(Redirect::to("/locations"))
+}
+
+pub async fn get_types_page(State(state): State<AppState>, user: User) -> AppResult<Html<String>> {
+    let types = get_activity_types(&state.pool, user.id).await?;
+    Ok(state.render("types.html", context!(user => user, types => types)))
+}
+
+pub async fn get_edit_type(
+    Query(q): Query<AddFormStruct>,
+    State(state): State<AppState>,
+    user: User,
+) -> AppResult<Html<String>> {
+    let form = match q.id {
+        Some(id) => ActivityTypeForm::from(&find_type(&state, &user, id).await?),
+        None => ActivityTypeForm {
+            color: "#FFFFFF".to_owned(),
+            ..ActivityTypeForm::default()
+        },
+    };
+    Ok(render_edit_type(
+        &state,
+        &user,
+        form,
+        FieldErrors::default(),
+    ))
+}
+
+fn render_edit_type(
+    state: &AppState,
+    user: &User,
+    form: ActivityTypeForm,
+    errors: FieldErrors,
+) -> Html<String> {
+    state.render(
+        "edit_type.html",
+        context!(user => user, activity_type => form, errors => errors),
+    )
+}
+
+async fn find_type(state: &AppState, user: &User, id: i64) -> AppResult<ActivityType> {
+    get_activity_type(&state.pool, user.id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Type {} does not exist", id)))
+}
+
+pub async fn post_edit_type(
+    State(state): State<AppState>,
+    user: User,
+    form: Result<Form<ActivityTypeForm>, FormRejection>,
+) -> AppResult<Response> {
+    let Form(form) = form?;
+    let activity_type = match form.parse() {
+        Ok(activity_type) => activity_type,
+        Err(errors) => {
+            let html = render_edit_type(&state, &user, form, errors);
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, html).into_response());
+        }
+    };
+
+    let existing = find_activity_type_id(&state.pool, user.id, &activity_type.name).await?;
+    if existing.is_some() && existing != activity_type.id {
+        let mut errors = FieldErrors::default();
+        errors.add("name", "A type with that name already exists");
+        let html = render_edit_type(&state, &user, form, errors);
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, html).into_response());
+    }
+    if let Some(id) = activity_type.id {
+        find_type(&state, &user, id).await?;
+    }
+    save_activity_type(&state.pool, user.id, activity_type).await?;
+    Ok(Redirect::to("/types").into_response())
+}
+
+pub async fn post_delete_type(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Redirect> {
+    let types = get_activity_types(&state.pool, user.id).await?;
+    let usage = types
+        .iter()
+        .find(|x| x.activity_type.id == Some(id))
+        .ok_or_else(|| AppError::NotFound(format!("Type {} does not exist", id)))?;
+    if usage.activities > 0 {
+        return Err(AppError::BadRequest(format!(
+            "{} is used by {} activities and can't be deleted. Archive it instead.",
+            usage.activity_type.name, usage.activities
+        )));
+    }
+    delete_activity_type(&state.pool, user.id, id).await?;
+
+    Ok(Redirect::to("/types"))
+}
+
+pub async fn get_entry_templates_page(
+    State(state): State<AppState>,
+    user: User,
+) -> AppResult<Html<String>> {
+    let entry_templates = get_entry_templates(&state.pool, user.id).await?;
+    Ok(state.render(
+        "entry_templates.html",
+        context!(user => user, entry_templates => entry_templates),
+    ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct EditEntryTemplateQuery {
+    id: Option<i64>,
+    /// Fill in a new template from this activity.
+    activity: Option<i64>,
+}
+
+pub async fn get_edit_entry_template(
+    Query(q): Query<EditEntryTemplateQuery>,
+    State(state): State<AppState>,
+    user: User,
+) -> AppResult<Html<String>> {
+    let template = match (q.id, q.activity) {
+        (Some(id), _) => find_entry_template(&state, &user, id).await?,
+        (None, Some(id)) => {
+            let activity = find_activity(&state, &user, id).await?;
+            EntryTemplate {
+                id: None,
+                name: "".to_owned(),
+                r#type: activity.r#type,
+                location: activity.location,
+                duration_hours: activity.duration_hours,
+                description: activity.description,
+                is_default: false,
+            }
+        }
+        (None, None) => EntryTemplate::default(),
+    };
+    render_edit_entry_template(
+        &state,
+        &user,
+        EntryTemplateForm::from(&template),
+        FieldErrors::default(),
+    )
+    .await
+}
+
+async fn render_edit_entry_template(
+    state: &AppState,
+    user: &User,
+    form: EntryTemplateForm,
+    errors: FieldErrors,
+) -> AppResult<Html<String>> {
+    let activity_types = get_all_types(&state.pool, user.id).await?;
+    let locations = get_all_locations(&state.pool, user.id).await?;
+    Ok(state.render(
+        "edit_entry_template.html",
+        context!(
+            user => user,
+            entry_template => form,
+            errors => errors,
             activity_types => activity_types,
             locations => locations,
         ),
+    ))
+}
+
+async fn find_entry_template(state: &AppState, user: &User, id: i64) -> AppResult<EntryTemplate> {
+    get_entry_template(&state.pool, user.id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Template {} does not exist", id)))
+}
+
+pub async fn post_edit_entry_template(
+    State(state): State<AppState>,
+    user: User,
+    form: Result<Form<EntryTemplateForm>, FormRejection>,
+) -> AppResult<Response> {
+    let Form(form) = form?;
+    let template = match form.parse() {
+        Ok(template) => template,
+        Err(errors) => {
+            let html = render_edit_entry_template(&state, &user, form, errors).await?;
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, html).into_response());
+        }
+    };
+
+    let existing = find_entry_template_id(&state.pool, user.id, &template.name).await?;
+    if existing.is_some() && existing != template.id {
+        let mut errors = FieldErrors::default();
+        errors.add("name", "A template with that name already exists");
+        let html = render_edit_entry_template(&state, &user, form, errors).await?;
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, html).into_response());
+    }
+    if let Some(id) = template.id {
+        find_entry_template(&state, &user, id).await?;
+    }
+    save_entry_template(&state.pool, user.id, template).await?;
+    Ok(Redirect::to("/entry-templates").into_response())
+}
+
+pub async fn post_delete_entry_template(
+    State(state): State<AppState>,
+    user: User,
+    Path(id): Path<i64>,
+) -> AppResult<Redirect> {
+    find_entry_template(&state, &user, id).await?;
+    delete_entry_template(&state.pool, user.id, id).await?;
+    Ok(Redirect::to("/entry-templates"))
+}
+
+pub async fn get_export(State(state): State<AppState>, user: User) -> AppResult<Response> {
+    let activities = get_activities_from(
+        &state.pool,
+        user.id,
+        None,
+        None,
+        &ActivityFilter::default(),
+        None,
     )
+    .await?;
+    let mut body = vec![];
+    write_csv(&activities, &mut body)?;
+
+    Ok((
+        [
+            (CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (CONTENT_DISPOSITION, "attachment; filename=\"snowlog.csv\""),
+        ],
+        body,
+    )
+        .into_response())
 }
 
-pub async fn post_edit(State(state): State<AppState>, Form(activity): Form<Activity>) -> Redirect {
-    match activity.id {
+/// Where the calendar feed is served, see [`get_calendar_feed`].
+pub const CALENDAR_FEED_PATH: &str = "/activities.ics";
+
+pub async fn get_settings_page(
+    State(state): State<AppState>,
+    user: User,
+    headers: HeaderMap,
+) -> AppResult<Html<String>> {
+    let token = match get_feed_token(&state.pool, user.id).await? {
+        Some(token) => token,
         None => {
-            insert_activity(&state.pool, activity).await.unwrap();
-            Redirect::to("/")
+            let token = new_token();
+            set_feed_token(&state.pool, user.id, &token).await?;
+            token
         }
-        Some(id) => {
-            update_activity(&state.pool, activity).await.unwrap();
-            Redirect::to(&format!("/#{}", id))
+    };
+    // Calendar apps need the whole URL. Secure cookies mean the site is served
+    // over HTTPS.
+    let scheme = if state.settings.secure_cookies {
+        "https"
+    } else {
+        "http"
+    };
+    let host = headers
+        .get(HOST)
+        .and_then(|x| x.to_str().ok())
+        .unwrap_or("localhost");
+    let feed_url = format!(
+        "{}://{}{}?token={}",
+        scheme, host, CALENDAR_FEED_PATH, token
+    );
+
+    Ok(state.render(
+        "settings.html",
+        context!(
+            user => user,
+            feed_url => feed_url,
+            season => state.settings.current_season(),
+        ),
+    ))
+}
+
+pub async fn post_feed_token(State(state): State<AppState>, user: User) -> AppResult<Redirect> {
+    set_feed_token(&state.pool, user.id, &new_token()).await?;
+    Ok(Redirect::to("/settings"))
+}
+
+#[derive(serde::Deserialize)]
+pub struct CalendarFeedQuery {
+    /// The secret token of the user's feed, see [`get_settings_page`].
+    token: String,
+    /// Only activities of these types, or all of them when empty.
+    #[serde(default, rename = "type")]
+    types: Vec<String>,
+    /// Only the season starting this year, or all time.
+    season: Option<i32>,
+}
+
+/// The activities as an iCalendar feed. Calendar apps can't log in, so the
+/// user is found from the token in the URL instead of the session.
+pub async fn get_calendar_feed(
+    State(state): State<AppState>,
+    RawQuery(query): RawQuery,
+) -> AppResult<Response> {
+    let q: CalendarFeedQuery = parse_query(query.as_deref())?;
+    let user = get_feed_token_user(&state.pool, &q.token)
+        .await?
+        .ok_or_else(|| AppError::NotFound("There is no calendar feed with this token".into()))?;
+    let (from, to, name) = match q.season {
+        Some(year) => {
+            let season = Season::new(year, state.settings.season_start)
+                .ok_or_else(|| AppError::NotFound(format!("There is no season {}", year)))?;
+            let name = format!("Snowlog {}", season.label());
+            (Some(season.start), Some(season.end), name)
+        }
+        None => (None, None, "Snowlog".to_owned()),
+    };
+    let filter = ActivityFilter {
+        types: q.types,
+        ..ActivityFilter::default()
+    };
+    let activities = get_activities_from(&state.pool, user.id, from, to, &filter, None).await?;
+    let body = write_calendar(
+        &activities,
+        &name,
+        state.settings.timezone,
+        Utc::now().naive_utc(),
+    );
+
+    Ok((
+        [
+            (CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (CONTENT_DISPOSITION, "inline; filename=\"snowlog.ics\""),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+pub async fn get_import(State(state): State<AppState>, user: User) -> Html<String> {
+    state.render("import.html", context!(user => user))
+}
+
+/// Handles both the uploaded file and the confirmation form on the preview
+/// page, which posts the same data back with `commit` set.
+pub async fn post_import(
+    State(state): State<AppState>,
+    user: User,
+    mut multipart: Multipart,
+) -> AppResult<Response> {
+    let mut data = vec![];
+    let mut commit = false;
+    while let Some(field) = multipart.next_field().await? {
+        match field.name() {
+            Some("file") | Some("data") => {
+                let bytes = field.bytes().await?;
+                if !bytes.is_empty() {
+                    data = bytes.to_vec();
+                }
+            }
+            Some("commit") => commit = true,
+            _ => {}
         }
     }
+
+    let render_error = |message: String| {
+        let html = state.render("import.html", context!(user => user, error => message));
+        Ok((StatusCode::BAD_REQUEST, html).into_response())
+    };
+    if data.is_empty() {
+        return render_error("Choose a CSV file to import".to_owned());
+    }
+    let Ok(text) = String::from_utf8(data) else {
+        return render_error("The file is not UTF-8 encoded text".to_owned());
+    };
+
+    let now = state.settings.local_now();
+    let result = if commit {
+        run_import(&state.pool, user.id, text.as_bytes(), now).await
+    } else {
+        preview_import(&state.pool, user.id, text.as_bytes(), now).await
+    };
+    let preview = match result {
+        Ok(preview) => preview,
+        Err(e) if e.is::<sqlx::Error>() => return Err(e.into()),
+        Err(e) => return render_error(format!("{:#}", e)),
+    };
+
+    Ok(state
+        .render(
+            "import.html",
+            context!(user => user, preview => preview, data => text, committed => commit),
+        )
+        .into_response())
 }
 
-pub async fn post_delete(State(state): State<AppState>, Path(id): Path<i64>) -> Redirect {
-    delete_activity(&state.pool, id).await.unwrap();
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::extract::DefaultBodyLimit;
+    use axum::http::header::COOKIE;
+    use axum::http::Request;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use futures_util::StreamExt;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::assets::AssetSource;
+    use crate::models::{create_session, create_user};
+    use crate::photos::PhotoStore;
+    use crate::settings::Settings;
+    use crate::templates::CachedEnvironment;
+
+    async fn read_form(body: String, limit: usize) -> (String, usize, bool) {
+        let app = Router::new()
+            .route(
+                "/",
+                post(|mut multipart: Multipart| async move {
+                    let (form, uploads) = read_activity_form(&mut multipart).await.unwrap();
+                    let result = (form.description, uploads.photos.len(), uploads.too_large);
+                    serde_json::to_string(&result).unwrap()
+                }),
+            )
+            .layer(DefaultBodyLimit::max(limit));
+        let chunks: Vec<Vec<u8>> = body.as_bytes().chunks(100).map(|x| x.to_vec()).collect();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/")
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=XYZ")
+            // Sent in chunks that arrive one at a time, like over the network,
+            // so that the limit is only reached once the photo is read.
+            .body(Body::wrap_stream(futures_util::stream::iter(chunks).then(
+                |chunk| async {
+                    tokio::task::yield_now().await;
+                    Ok::<_, std::io::Error>(chunk)
+                },
+            )))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn fields_are_kept_when_the_files_are_too_large() {
+        let photo = "x".repeat(2000);
+        let body = format!(
+            "--XYZ\r\n\
+            Content-Disposition: form-data; name=\"description\"\r\n\r\n\
+            Deep snow\r\n\
+            --XYZ\r\n\
+            Content-Disposition: form-data; name=\"photos\"; filename=\"a.jpg\"\r\n\
+            Content-Type: image/jpeg\r\n\r\n\
+            {}\r\n\
+            --XYZ--\r\n",
+            photo
+        );
+
+        let (description, photos, too_large) = read_form(body.clone(), 10_000).await;
+        assert_eq!(
+            ("Deep snow", 1, false),
+            (description.as_str(), photos, too_large)
+        );
+
+        let (description, photos, too_large) = read_form(body, 1000).await;
+        assert_eq!(
+            ("Deep snow", 0, true),
+            (description.as_str(), photos, too_large)
+        );
+    }
+
+    async fn send(app: &Router, method: &str, uri: &str) -> (StatusCode, String) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(HOST, "snow.example")
+            .header(COOKIE, "snowlog_session=alice-token")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    /// A router with alice logged in as `alice-token`, owning `activities`.
+    async fn setup(activities: Vec<Activity>) -> Router {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        let owner = create_user(&pool, "alice", "hash", NaiveDateTime::default())
+            .await
+            .unwrap();
+        let expires = "2100-01-01T00:00:00".parse().unwrap();
+        create_session(&pool, owner, "alice-token", expires)
+            .await
+            .unwrap();
+        for activity in activities {
+            insert_activity(&pool, owner, activity).await.unwrap();
+        }
+
+        let state = AppState {
+            pool,
+            environment: Box::leak(Box::new(CachedEnvironment::new(
+                AssetSource::new(None, "ui/jinja"),
+                false,
+            ))),
+            settings: Arc::new(Settings::default()),
+            photos: Arc::new(PhotoStore::new(&std::env::temp_dir().join("snowlog-feed")).unwrap()),
+        };
+        Router::new()
+            .route("/season/:year", get(get_season))
+            .route(CALENDAR_FEED_PATH, get(get_calendar_feed))
+            .route("/settings", get(get_settings_page))
+            .route("/settings/feed-token", post(post_feed_token))
+            .with_state(state)
+    }
+
+    fn skis(date: &str) -> Activity {
+        Activity {
+            location: "Tryvann".into(),
+            duration_hours: Some(2.0),
+            ..crate::models::tests::activity(date, "Skis")
+        }
+    }
+
+    #[tokio::test]
+    async fn heatmap_years_include_days_before_the_season() {
+        // The 2025 season starts on October 1st.
+        let app = setup(vec![
+            skis("2025-03-01T10:00:00"),
+            skis("2025-11-01T10:00:00"),
+        ])
+        .await;
+
+        let (status, page) = send(&app, "GET", "/season/2025").await;
+        assert_eq!(StatusCode::OK, status);
+        assert!(page.contains("2025-11-01: 2.00 hours"));
+        assert!(!page.contains("2025-03-01"));
 
-    Redirect::to("/")
+        let (status, page) = send(&app, "GET", "/season/2025?heatmap_range=year").await;
+        assert_eq!(StatusCode::OK, status);
+        assert!(page.contains("2025-03-01: 2.00 hours"));
+        assert!(page.contains("2025-11-01: 2.00 hours"));
+
+        let query = "heatmap_range=year&from=2025-06-01";
+        let (_, page) = send(&app, "GET", &format!("/season/2025?{}", query)).await;
+        assert!(page.contains("2025-03-01: nothing"));
+        assert!(page.contains("2025-11-01: 2.00 hours"));
+    }
+
+    #[tokio::test]
+    async fn filters_with_only_an_end_date_include_old_activities() {
+        let app = setup(vec![
+            skis("1965-02-01T10:00:00"),
+            skis("2025-11-01T10:00:00"),
+        ])
+        .await;
+
+        let (status, page) = send(&app, "GET", "/season/2025?to=1969-12-31").await;
+        assert_eq!(StatusCode::OK, status);
+        assert!(page.contains("<td>1 days</td>"));
+        assert!(page.contains("1965"));
+        assert!(!page.contains("2025-11-01: 2.00 hours"));
+    }
+
+    #[tokio::test]
+    async fn calendar_feeds_use_a_token_that_can_be_replaced() {
+        let app = setup(vec![skis("2025-01-05T10:00:00")]).await;
+        let feed_url = |page: &str| {
+            let page = page.replace("&#x2f;", "/");
+            let start = page
+                .find("https://snow.example/activities.ics?token=")
+                .unwrap();
+            page[start..].split('"').next().unwrap().to_owned()
+        };
+
+        // The session alone is not enough.
+        let (status, _) = send(&app, "GET", "/activities.ics").await;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        let (status, _) = send(&app, "GET", "/activities.ics?token=guess").await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+
+        let (_, page) = send(&app, "GET", "/settings").await;
+        let old = feed_url(&page);
+        let (_, page) = send(&app, "GET", "/settings").await;
+        assert_eq!(old, feed_url(&page));
+        let old = old.trim_start_matches("https://snow.example").to_owned();
+        let (status, body) = send(&app, "GET", &old).await;
+        assert_eq!(StatusCode::OK, status);
+        assert!(body.contains("SUMMARY:Skis\r\n"));
+        let (status, body) = send(&app, "GET", &format!("{}&type=Board", old)).await;
+        assert_eq!(StatusCode::OK, status);
+        assert!(!body.contains("BEGIN:VEVENT"));
+        let (_, body) = send(&app, "GET", &format!("{}&season=2024&type=Skis", old)).await;
+        assert!(body.contains("BEGIN:VEVENT"));
+        let (_, body) = send(&app, "GET", &format!("{}&season=2025", old)).await;
+        assert!(!body.contains("BEGIN:VEVENT"));
+
+        let (status, _) = send(&app, "POST", "/settings/feed-token").await;
+        assert_eq!(StatusCode::SEE_OTHER, status);
+        let (_, page) = send(&app, "GET", "/settings").await;
+        let new = feed_url(&page);
+        assert_ne!(old, new);
+        let (status, _) = send(&app, "GET", &old).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        let new = new.trim_start_matches("https://snow.example").to_owned();
+        let (status, _) = send(&app, "GET", &new).await;
+        assert_eq!(StatusCode::OK, status);
+    }
 }
@@ -0,0 +1,150 @@
+use std::cmp::Reverse;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// The month and day every season starts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeasonStart {
+    pub month: u32,
+    pub day: u32,
+}
+
+/// A season runs from the configured start date in `year` until the same date
+/// the year after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Season {
+    pub year: i32,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+impl Season {
+    pub fn new(year: i32, season_start: SeasonStart) -> Option<Self> {
+        let SeasonStart { month, day } = season_start;
+        let start = NaiveDate::from_ymd_opt(year, month, day)?;
+        let end = NaiveDate::from_ymd_opt(year + 1, month, day)?;
+        Some(Season {
+            year,
+            start: start.and_hms_opt(0, 0, 0)?,
+            end: end.and_hms_opt(0, 0, 0)?,
+        })
+    }
+
+    pub fn containing(date: NaiveDate, season_start: SeasonStart) -> Self {
+        let start_of_year =
+            NaiveDate::from_ymd_opt(date.year(), season_start.month, season_start.day)
+                .expect("Season start is a valid date");
+        let year = if date >= start_of_year {
+            date.year()
+        } else {
+            date.year() - 1
+        };
+        Season::new(year, season_start).expect("Season containing a valid date is valid")
+    }
+
+    fn season_start(&self) -> SeasonStart {
+        SeasonStart {
+            month: self.start.month(),
+            day: self.start.day(),
+        }
+    }
+
+    pub fn previous(&self) -> Option<Self> {
+        Season::new(self.year - 1, self.season_start())
+    }
+
+    pub fn next(&self) -> Option<Self> {
+        Season::new(self.year + 1, self.season_start())
+    }
+
+    /// Human readable name, e.g. `2024/25`, or just `2024` for seasons that
+    /// follow the calendar year.
+    pub fn label(&self) -> String {
+        if self.season_start() == (SeasonStart { month: 1, day: 1 }) {
+            self.year.to_string()
+        } else {
+            format!("{}/{:02}", self.year, (self.year + 1).rem_euclid(100))
+        }
+    }
+}
+
+impl Serialize for Season {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Season", 4)?;
+        state.serialize_field("year", &self.year)?;
+        state.serialize_field("label", &self.label())?;
+        state.serialize_field("start", &self.start)?;
+        state.serialize_field("end", &self.end)?;
+        state.end()
+    }
+}
+
+/// Returns the distinct seasons the given dates fall into, newest first.
+pub fn seasons_for_dates(
+    dates: impl IntoIterator<Item = NaiveDate>,
+    season_start: SeasonStart,
+) -> Vec<Season> {
+    let mut seasons: Vec<Season> = dates
+        .into_iter()
+        .map(|date| Season::containing(date, season_start))
+        .collect();
+    seasons.sort_by_key(|season| Reverse(season.year));
+    seasons.dedup();
+    seasons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OCTOBER: SeasonStart = SeasonStart { month: 10, day: 1 };
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn season_boundaries() {
+        assert_eq!(2024, Season::containing(date("2024-10-01"), OCTOBER).year);
+        assert_eq!(2023, Season::containing(date("2024-09-30"), OCTOBER).year);
+        assert_eq!(2024, Season::containing(date("2025-03-15"), OCTOBER).year);
+
+        let season = Season::new(2024, OCTOBER).unwrap();
+        assert_eq!(
+            date("2024-10-01").and_hms_opt(0, 0, 0).unwrap(),
+            season.start
+        );
+        assert_eq!(date("2025-10-01").and_hms_opt(0, 0, 0).unwrap(), season.end);
+        assert_eq!("2024/25", season.label());
+        assert_eq!("1999/00", Season::new(1999, OCTOBER).unwrap().label());
+        assert_eq!(2023, season.previous().unwrap().year);
+    }
+
+    #[test]
+    fn custom_season_start() {
+        let southern = SeasonStart { month: 6, day: 15 };
+        assert_eq!(2024, Season::containing(date("2024-06-15"), southern).year);
+        assert_eq!(2023, Season::containing(date("2024-06-14"), southern).year);
+        let next = Season::new(2024, southern).unwrap().next().unwrap();
+        assert_eq!(date("2025-06-15"), next.start.date());
+
+        let calendar = SeasonStart { month: 1, day: 1 };
+        assert_eq!("2024", Season::new(2024, calendar).unwrap().label());
+    }
+
+    #[test]
+    fn seasons_are_distinct_and_sorted() {
+        let seasons = seasons_for_dates(
+            [
+                date("2023-01-10"),
+                date("2024-12-24"),
+                date("2022-11-01"),
+                date("2025-02-01"),
+            ],
+            OCTOBER,
+        );
+        let years: Vec<i32> = seasons.iter().map(|s| s.year).collect();
+        assert_eq!(vec![2024, 2022], years);
+    }
+}
@@ -0,0 +1,373 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{NaiveDate, NaiveDateTime, SubsecRound, Utc};
+use chrono_tz::Tz;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::Level;
+
+use crate::season::{Season, SeasonStart};
+
+const DEFAULT_CONFIG_PATH: &str = "snowlog.toml";
+
+/// Application settings, read from an optional TOML file and overridden by
+/// environment variables and then by command line flags.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub season_start: SeasonStart,
+    pub timezone: Tz,
+    pub allow_registration: bool,
+    /// Whether the session cookie is only sent over HTTPS. Turn it off to log
+    /// in over plain HTTP.
+    pub secure_cookies: bool,
+    /// Where uploaded files are stored. Defaults to a directory next to the
+    /// database, see [`Settings::data_dir`].
+    pub data_dir: Option<PathBuf>,
+    /// Largest edit form accepted, with its track and photos, in megabytes.
+    pub max_upload_mb: usize,
+    pub listen: SocketAddr,
+    pub database_url: String,
+    /// Directories to read the UI files from. When they are not set, release
+    /// builds use the files compiled into the binary.
+    pub static_dir: Option<PathBuf>,
+    pub templates_dir: Option<PathBuf>,
+    pub log_level: Level,
+    /// The file the settings were read from, if any.
+    pub config_file: Option<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            season_start: SeasonStart { month: 10, day: 1 },
+            timezone: chrono_tz::Europe::Oslo,
+            allow_registration: true,
+            secure_cookies: true,
+            data_dir: None,
+            max_upload_mb: 64,
+            listen: SocketAddr::from(([0, 0, 0, 0], 8080)),
+            database_url: "sqlite://db.sqlite".to_owned(),
+            static_dir: None,
+            templates_dir: None,
+            log_level: Level::INFO,
+            config_file: None,
+        }
+    }
+}
+
+/// Settings given on the command line. They take precedence over both the
+/// environment and the file.
+#[derive(Debug, Default, clap::Args)]
+pub struct SettingsArgs {
+    /// Settings file to read instead of snowlog.toml.
+    #[arg(long, global = true, env = "SNOWLOG_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Address and port for the web server, e.g. 127.0.0.1:8080.
+    #[arg(long, global = true)]
+    pub listen: Option<SocketAddr>,
+    #[arg(long, global = true)]
+    pub database_url: Option<String>,
+    /// Where uploaded photos are stored.
+    #[arg(long, global = true)]
+    pub data_dir: Option<PathBuf>,
+    /// Largest upload accepted, in megabytes.
+    #[arg(long, global = true)]
+    pub max_upload_mb: Option<usize>,
+    #[arg(long, global = true)]
+    pub static_dir: Option<PathBuf>,
+    #[arg(long, global = true)]
+    pub templates_dir: Option<PathBuf>,
+    /// One of error, warn, info, debug or trace.
+    #[arg(long, global = true)]
+    pub log_level: Option<Level>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct SettingsFile {
+    timezone: Option<String>,
+    allow_registration: Option<bool>,
+    secure_cookies: Option<bool>,
+    data_dir: Option<PathBuf>,
+    max_upload_mb: Option<usize>,
+    listen: Option<SocketAddr>,
+    database_url: Option<String>,
+    static_dir: Option<PathBuf>,
+    templates_dir: Option<PathBuf>,
+    log_level: Option<String>,
+    season: SeasonFile,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct SeasonFile {
+    start_month: Option<u32>,
+    start_day: Option<u32>,
+}
+
+impl Settings {
+    /// Loads settings from the given file (or `snowlog.toml` if it exists),
+    /// then applies environment variables and command line flags on top.
+    pub fn load(args: &SettingsArgs) -> Result<Self> {
+        let path = match &args.config {
+            Some(path) => Some(path.clone()),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => Some(DEFAULT_CONFIG_PATH.into()),
+            None => None,
+        };
+
+        let mut settings = Settings::default();
+        if let Some(path) = path {
+            settings.apply_file(read_file(&path)?)?;
+            settings.config_file = Some(path);
+        }
+        settings.apply_env(|key| std::env::var(key).ok())?;
+        settings.apply_args(args);
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn apply_file(&mut self, file: SettingsFile) -> Result<()> {
+        if let Some(tz) = file.timezone {
+            self.timezone = parse_timezone(&tz)?;
+        }
+        if let Some(allow) = file.allow_registration {
+            self.allow_registration = allow;
+        }
+        if let Some(secure) = file.secure_cookies {
+            self.secure_cookies = secure;
+        }
+        if let Some(dir) = file.data_dir {
+            self.data_dir = Some(dir);
+        }
+        if let Some(mb) = file.max_upload_mb {
+            self.max_upload_mb = mb;
+        }
+        if let Some(listen) = file.listen {
+            self.listen = listen;
+        }
+        if let Some(url) = file.database_url {
+            self.database_url = url;
+        }
+        if let Some(dir) = file.static_dir {
+            self.static_dir = Some(dir);
+        }
+        if let Some(dir) = file.templates_dir {
+            self.templates_dir = Some(dir);
+        }
+        if let Some(level) = file.log_level {
+            self.log_level = parse_env("log_level", &level)?;
+        }
+        if let Some(month) = file.season.start_month {
+            self.season_start.month = month;
+        }
+        if let Some(day) = file.season.start_day {
+            self.season_start.day = day;
+        }
+        Ok(())
+    }
+
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(tz) = var("SNOWLOG_TIMEZONE") {
+            self.timezone = parse_timezone(&tz)?;
+        }
+        if let Some(allow) = var("SNOWLOG_ALLOW_REGISTRATION") {
+            self.allow_registration = parse_env("SNOWLOG_ALLOW_REGISTRATION", &allow)?;
+        }
+        if let Some(secure) = var("SNOWLOG_SECURE_COOKIES") {
+            self.secure_cookies = parse_env("SNOWLOG_SECURE_COOKIES", &secure)?;
+        }
+        if let Some(dir) = var("SNOWLOG_DATA_DIR") {
+            self.data_dir = Some(PathBuf::from(dir));
+        }
+        if let Some(mb) = var("SNOWLOG_MAX_UPLOAD_MB") {
+            self.max_upload_mb = parse_env("SNOWLOG_MAX_UPLOAD_MB", &mb)?;
+        }
+        if let Some(listen) = var("SNOWLOG_LISTEN") {
+            self.listen = parse_env("SNOWLOG_LISTEN", &listen)?;
+        }
+        if let Some(url) = var("DATABASE_URL") {
+            self.database_url = url;
+        }
+        if let Some(dir) = var("SNOWLOG_STATIC_DIR") {
+            self.static_dir = Some(PathBuf::from(dir));
+        }
+        if let Some(dir) = var("SNOWLOG_TEMPLATES_DIR") {
+            self.templates_dir = Some(PathBuf::from(dir));
+        }
+        if let Some(level) = var("SNOWLOG_LOG_LEVEL") {
+            self.log_level = parse_env("SNOWLOG_LOG_LEVEL", &level)?;
+        }
+        if let Some(month) = var("SNOWLOG_SEASON_START_MONTH") {
+            self.season_start.month = parse_env("SNOWLOG_SEASON_START_MONTH", &month)?;
+        }
+        if let Some(day) = var("SNOWLOG_SEASON_START_DAY") {
+            self.season_start.day = parse_env("SNOWLOG_SEASON_START_DAY", &day)?;
+        }
+        Ok(())
+    }
+
+    fn apply_args(&mut self, args: &SettingsArgs) {
+        if let Some(listen) = args.listen {
+            self.listen = listen;
+        }
+        if let Some(url) = &args.database_url {
+            self.database_url = url.clone();
+        }
+        if let Some(dir) = &args.data_dir {
+            self.data_dir = Some(dir.clone());
+        }
+        if let Some(mb) = args.max_upload_mb {
+            self.max_upload_mb = mb;
+        }
+        if let Some(dir) = &args.static_dir {
+            self.static_dir = Some(dir.clone());
+        }
+        if let Some(dir) = &args.templates_dir {
+            self.templates_dir = Some(dir.clone());
+        }
+        if let Some(level) = args.log_level {
+            self.log_level = level;
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let SeasonStart { month, day } = self.season_start;
+        // 2001 is not a leap year, so February 29th is rejected as well.
+        if NaiveDate::from_ymd_opt(2001, month, day).is_none() {
+            bail!("Season start month {month} day {day} is not a valid date");
+        }
+        Ok(())
+    }
+
+    /// The current wall clock time in the configured timezone.
+    pub fn local_now(&self) -> NaiveDateTime {
+        Utc::now()
+            .with_timezone(&self.timezone)
+            .naive_local()
+            .trunc_subsecs(0)
+    }
+
+    pub fn current_season(&self) -> Season {
+        Season::containing(self.local_now().date(), self.season_start)
+    }
+
+    /// Where uploads go. Unless configured, this is a "files" directory next
+    /// to the database.
+    pub fn data_dir(&self) -> PathBuf {
+        self.data_dir.clone().unwrap_or_else(|| {
+            sqlite_path(&self.database_url)
+                .and_then(|path| path.parent())
+                .unwrap_or(Path::new(""))
+                .join("files")
+        })
+    }
+
+    /// The settings in the format of the settings file.
+    pub fn to_toml(&self) -> Result<String> {
+        let file = SettingsFile {
+            timezone: Some(self.timezone.name().to_owned()),
+            allow_registration: Some(self.allow_registration),
+            secure_cookies: Some(self.secure_cookies),
+            data_dir: Some(self.data_dir()),
+            max_upload_mb: Some(self.max_upload_mb),
+            listen: Some(self.listen),
+            database_url: Some(self.database_url.clone()),
+            static_dir: self.static_dir.clone(),
+            templates_dir: self.templates_dir.clone(),
+            log_level: Some(self.log_level.to_string().to_lowercase()),
+            season: SeasonFile {
+                start_month: Some(self.season_start.month),
+                start_day: Some(self.season_start.day),
+            },
+        };
+        Ok(toml::to_string(&file)?)
+    }
+}
+
+/// The file a `sqlite://` URL points to.
+pub fn sqlite_path(db_url: &str) -> Option<&Path> {
+    let regex = Regex::new("sqlite://(.*)").unwrap();
+    regex
+        .captures(db_url)
+        .map(|m| Path::new(m.get(1).unwrap().as_str()))
+}
+
+fn read_file(path: &Path) -> Result<SettingsFile> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+    toml::from_str(&contents).with_context(|| format!("Failed to parse {:?}", path))
+}
+
+fn parse_timezone(value: &str) -> Result<Tz> {
+    Tz::from_str(value).map_err(|e| anyhow!("Invalid timezone '{}': {}", value, e))
+}
+
+fn parse_env<T: FromStr>(key: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("{} has an invalid value '{}'", key, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_and_env_overrides() {
+        let mut settings = Settings::default();
+        let file: SettingsFile = toml::from_str(
+            r#"
+                timezone = "Australia/Sydney"
+                [season]
+                start_month = 6
+            "#,
+        )
+        .unwrap();
+        settings.apply_file(file).unwrap();
+        assert_eq!(chrono_tz::Australia::Sydney, settings.timezone);
+        assert_eq!(SeasonStart { month: 6, day: 1 }, settings.season_start);
+
+        settings
+            .apply_env(|key| (key == "SNOWLOG_SEASON_START_DAY").then(|| "15".to_owned()))
+            .unwrap();
+        assert_eq!(SeasonStart { month: 6, day: 15 }, settings.season_start);
+        settings.validate().unwrap();
+
+        settings
+            .apply_env(|key| {
+                (key == "DATABASE_URL").then(|| "sqlite:///var/lib/snowlog/db.sqlite".to_owned())
+            })
+            .unwrap();
+        assert_eq!(PathBuf::from("/var/lib/snowlog/files"), settings.data_dir());
+        settings.apply_args(&SettingsArgs {
+            listen: Some("127.0.0.1:3000".parse().unwrap()),
+            log_level: Some(Level::DEBUG),
+            ..Default::default()
+        });
+        assert_eq!(3000, settings.listen.port());
+        settings
+            .apply_env(|key| (key == "SNOWLOG_MAX_UPLOAD_MB").then(|| "20".to_owned()))
+            .unwrap();
+        assert_eq!(20, settings.max_upload_mb);
+
+        let printed: SettingsFile = toml::from_str(&settings.to_toml().unwrap()).unwrap();
+        assert_eq!(Some("debug".to_owned()), printed.log_level);
+        assert_eq!(Some(15), printed.season.start_day);
+        assert_eq!(Some("Australia/Sydney".to_owned()), printed.timezone);
+        assert_eq!(Some(true), printed.secure_cookies);
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let mut settings = Settings::default();
+        assert!(settings
+            .apply_env(|key| (key == "SNOWLOG_TIMEZONE").then(|| "Mars/Olympus".to_owned()))
+            .is_err());
+
+        settings.season_start = SeasonStart { month: 2, day: 29 };
+        assert!(settings.validate().is_err());
+    }
+}
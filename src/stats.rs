@@ -0,0 +1,205 @@
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+use crate::models::{DailyTotal, WeekdayStats};
+use crate::season::{Season, SeasonStart};
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Streak {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub days: i64,
+}
+
+/// Finds runs of consecutive days with activities, longest first. Ties are
+/// broken by recency.
+pub fn longest_streaks(dates: &[NaiveDate], limit: usize) -> Vec<Streak> {
+    let mut dates = dates.to_vec();
+    dates.sort();
+    dates.dedup();
+
+    let mut streaks: Vec<Streak> = vec![];
+    for date in dates {
+        match streaks.last_mut() {
+            Some(streak) if streak.end + Duration::days(1) == date => {
+                streak.end = date;
+                streak.days += 1;
+            }
+            _ => streaks.push(Streak {
+                start: date,
+                end: date,
+                days: 1,
+            }),
+        }
+    }
+
+    streaks.sort_by(|a, b| b.days.cmp(&a.days).then(b.end.cmp(&a.end)));
+    streaks.truncate(limit);
+    streaks
+}
+
+#[derive(Debug, Serialize)]
+pub struct SeasonTotal {
+    pub season: Season,
+    pub days: i64,
+    pub activities: i64,
+    pub hours: f64,
+    /// Change in hours compared to the season before, if there was one.
+    pub hours_change: Option<f64>,
+}
+
+/// Sums the daily totals per season, newest season first.
+pub fn season_totals(totals: &[DailyTotal], season_start: SeasonStart) -> Vec<SeasonTotal> {
+    let mut seasons: Vec<SeasonTotal> = vec![];
+    for total in totals {
+        let season = Season::containing(total.date, season_start);
+        match seasons.iter_mut().find(|x| x.season == season) {
+            Some(x) => {
+                x.days += 1;
+                x.activities += total.activities;
+                x.hours += total.hours;
+            }
+            None => seasons.push(SeasonTotal {
+                season,
+                days: 1,
+                activities: total.activities,
+                hours: total.hours,
+                hours_change: None,
+            }),
+        }
+    }
+
+    seasons.sort_by_key(|x| x.season.year);
+    for i in 1..seasons.len() {
+        if seasons[i - 1].season.next() == Some(seasons[i].season) {
+            seasons[i].hours_change = Some(seasons[i].hours - seasons[i - 1].hours);
+        }
+    }
+    seasons.reverse();
+    seasons
+}
+
+/// Formats a `YYYY-MM` month as for example "January 2025".
+pub fn month_label(month: &str) -> String {
+    NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+        .map(|date| date.format("%B %Y").to_string())
+        .unwrap_or_else(|_| month.to_owned())
+}
+
+#[derive(Debug, Serialize)]
+pub struct NamedWeekday {
+    pub name: &'static str,
+    pub days: i64,
+    pub hours: f64,
+}
+
+/// Lists every day of the week starting on Monday, including days without
+/// any activities.
+pub fn weekdays(stats: &[WeekdayStats]) -> Vec<NamedWeekday> {
+    WEEKDAYS
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            // sqlite counts from Sunday, while the list starts on Monday.
+            let weekday = (i as i64 + 1) % 7;
+            let stat = stats.iter().find(|x| x.weekday == weekday);
+            NamedWeekday {
+                name,
+                days: stat.map(|x| x.days).unwrap_or_default(),
+                hours: stat.map(|x| x.hours).unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn streaks_are_consecutive_days() {
+        let dates = [
+            "2025-01-01",
+            "2025-01-02",
+            "2025-01-02",
+            "2025-01-03",
+            "2025-01-05",
+            "2025-02-10",
+            "2025-02-11",
+        ]
+        .map(date);
+        let streaks = longest_streaks(&dates, 2);
+        assert_eq!(
+            vec![
+                Streak {
+                    start: date("2025-01-01"),
+                    end: date("2025-01-03"),
+                    days: 3
+                },
+                Streak {
+                    start: date("2025-02-10"),
+                    end: date("2025-02-11"),
+                    days: 2
+                },
+            ],
+            streaks
+        );
+    }
+
+    #[test]
+    fn seasons_are_compared_with_the_previous_one() {
+        let start = SeasonStart { month: 10, day: 1 };
+        let total = |day: &str, hours: f64| DailyTotal {
+            date: date(day),
+            activities: 1,
+            hours,
+            average_score: None,
+        };
+        let totals = [
+            total("2021-12-01", 1.0),
+            total("2024-01-01", 2.0),
+            total("2024-02-01", 3.0),
+            total("2024-11-01", 4.0),
+        ];
+        let seasons = season_totals(&totals, start);
+        let summary: Vec<_> = seasons
+            .iter()
+            .map(|x| (x.season.year, x.days, x.hours, x.hours_change))
+            .collect();
+        assert_eq!(
+            vec![
+                (2024, 1, 4.0, Some(-1.0)),
+                (2023, 2, 5.0, None),
+                (2021, 1, 1.0, None),
+            ],
+            summary
+        );
+    }
+
+    #[test]
+    fn weekdays_start_on_monday() {
+        let stats = [WeekdayStats {
+            weekday: 0,
+            days: 2,
+            hours: 5.0,
+        }];
+        let weekdays = weekdays(&stats);
+        assert_eq!(7, weekdays.len());
+        assert_eq!(("Sunday", 2), (weekdays[6].name, weekdays[6].days));
+        assert_eq!(0, weekdays[0].days);
+    }
+}
@@ -11,6 +11,8 @@ use tracing::warn;
 
 use anyhow::{Context, Result};
 
+use crate::assets::{AssetSource, Templates};
+
 fn value_to_float(value: Value) -> Result<f64, Error> {
     Ok(match value.kind() {
         ValueKind::Number => value.try_into().unwrap(),
@@ -85,24 +87,28 @@ fn hourminutes(value: Option<String>) -> Result<Value, Error> {
 
 #[derive(Clone)]
 pub struct CachedEnvironment {
+    source: AssetSource,
     environment: Option<Environment<'static>>,
 }
 
 impl CachedEnvironment {
-    pub fn new(use_cache: bool) -> Self {
+    pub fn new(source: AssetSource, use_cache: bool) -> Self {
         CachedEnvironment {
             environment: if use_cache {
-                Some(create_environment().expect("Failed to create environment"))
+                Some(create_environment(&source).expect("Failed to create environment"))
             } else {
                 None
             },
+            source,
         }
     }
 
     pub fn render<S: Serialize>(&self, template_path: &str, context: S) -> Html<String> {
         let env = match &self.environment {
             Some(cached) => Cow::Borrowed(cached),
-            None => Cow::Owned(create_environment().expect("Failed to create environment")),
+            None => {
+                Cow::Owned(create_environment(&self.source).expect("Failed to create environment"))
+            }
         };
 
         let template = match env.get_template(template_path) {
@@ -116,7 +122,7 @@ impl CachedEnvironment {
     }
 }
 
-fn create_environment<'source>() -> Result<Environment<'source>> {
+fn create_environment<'source>(source: &AssetSource) -> Result<Environment<'source>> {
     let mut environment = Environment::new();
     let uuid = &uuid::Uuid::new_v4().to_string()[..8];
     environment.add_global("buildNumber", uuid);
@@ -128,7 +134,19 @@ fn create_environment<'source>() -> Result<Environment<'source>> {
     environment.add_filter("markdown", markdown);
     environment.add_filter("floatfmt", |f: f64| format!("{:.2}", f));
     environment.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
-    for file in fs::read_dir("ui/jinja").context("ui/jinja read dir failed")? {
+    let dir = match source {
+        AssetSource::Disk(dir) => dir,
+        AssetSource::Embedded => {
+            for name in Templates::iter() {
+                let file = Templates::get(&name).expect("Listed template exists");
+                let contents = String::from_utf8(file.data.into_owned())
+                    .with_context(|| format!("Template {} is not UTF-8", name))?;
+                environment.add_template_owned(name.into_owned(), contents)?;
+            }
+            return Ok(environment);
+        }
+    };
+    for file in fs::read_dir(dir).with_context(|| format!("{:?} read dir failed", dir))? {
         let file = file?;
         let name = file
             .path()
@@ -0,0 +1,292 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use fitparser::profile::MesgNum;
+use fitparser::Value;
+
+use crate::models::TrackStats;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+/// Elevation changes smaller than this are treated as GPS noise.
+const ELEVATION_NOISE_M: f64 = 5.0;
+/// A descent has to drop at least this much to count as a run.
+const MIN_RUN_DROP_M: f64 = 50.0;
+/// Speeds are measured over at least this many seconds so that single bad
+/// fixes don't show up as the max speed.
+const MIN_SPEED_WINDOW_SECONDS: f64 = 5.0;
+/// FIT stores coordinates as 32 bit "semicircles".
+const DEGREES_PER_SEMICIRCLE: f64 = 180.0 / 2_147_483_648.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackPoint {
+    pub time: Option<DateTime<Utc>>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub elevation: Option<f64>,
+}
+
+/// What a track file says about the activity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackSummary {
+    pub start: Option<DateTime<Utc>>,
+    pub duration_hours: Option<f64>,
+    pub stats: TrackStats,
+}
+
+/// Reads GPX or FIT data. FIT files are recognised by the `.FIT` signature in
+/// their header, anything else is parsed as GPX.
+pub fn parse_track(data: &[u8]) -> Result<Vec<TrackPoint>> {
+    let points = if data.get(8..12) == Some(b".FIT") {
+        parse_fit(data)?
+    } else {
+        parse_gpx(data)?
+    };
+    if points.len() < 2 {
+        bail!("The track has fewer than two points");
+    }
+    Ok(points)
+}
+
+fn parse_gpx(data: &[u8]) -> Result<Vec<TrackPoint>> {
+    let gpx = gpx::read(data).context("Not a valid GPX file")?;
+    let mut points = vec![];
+    for track in gpx.tracks {
+        for segment in track.segments {
+            for waypoint in segment.points {
+                let point = waypoint.point();
+                let time = match waypoint.time {
+                    Some(time) => Some(parse_gpx_time(&time.format()?)?),
+                    None => None,
+                };
+                points.push(TrackPoint {
+                    time,
+                    latitude: point.y(),
+                    longitude: point.x(),
+                    elevation: waypoint.elevation,
+                });
+            }
+        }
+    }
+    Ok(points)
+}
+
+fn parse_gpx_time(value: &str) -> Result<DateTime<Utc>> {
+    // The GPX crate writes years with a sign and six digits, e.g. +002025.
+    let value = match value.strip_prefix("+00") {
+        Some(rest) => rest,
+        None => value,
+    };
+    Ok(DateTime::parse_from_rfc3339(value)
+        .with_context(|| format!("Invalid time '{}' in track", value))?
+        .with_timezone(&Utc))
+}
+
+fn value_to_f64(value: &Value) -> Option<f64> {
+    match *value {
+        Value::SInt8(x) => Some(x as f64),
+        Value::UInt8(x) | Value::Byte(x) | Value::UInt8z(x) => Some(x as f64),
+        Value::SInt16(x) => Some(x as f64),
+        Value::UInt16(x) | Value::UInt16z(x) => Some(x as f64),
+        Value::SInt32(x) => Some(x as f64),
+        Value::UInt32(x) | Value::UInt32z(x) => Some(x as f64),
+        Value::SInt64(x) => Some(x as f64),
+        Value::UInt64(x) | Value::UInt64z(x) => Some(x as f64),
+        Value::Float32(x) => Some(x as f64),
+        Value::Float64(x) => Some(x),
+        _ => None,
+    }
+}
+
+fn parse_fit(data: &[u8]) -> Result<Vec<TrackPoint>> {
+    let records = fitparser::from_bytes(data).context("Not a valid FIT file")?;
+    let mut points = vec![];
+    for record in records.iter().filter(|x| x.kind() == MesgNum::Record) {
+        let mut time = None;
+        let (mut latitude, mut longitude) = (None, None);
+        let (mut altitude, mut enhanced_altitude) = (None, None);
+        for field in record.fields() {
+            match (field.name(), field.value()) {
+                ("timestamp", Value::Timestamp(x)) => time = Some(x.with_timezone(&Utc)),
+                ("position_lat", value) => latitude = value_to_f64(value),
+                ("position_long", value) => longitude = value_to_f64(value),
+                ("altitude", value) => altitude = value_to_f64(value),
+                ("enhanced_altitude", value) => enhanced_altitude = value_to_f64(value),
+                _ => {}
+            }
+        }
+        // Records without a position are from before the watch had a fix.
+        if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+            points.push(TrackPoint {
+                time,
+                latitude: latitude * DEGREES_PER_SEMICIRCLE,
+                longitude: longitude * DEGREES_PER_SEMICIRCLE,
+                elevation: enhanced_altitude.or(altitude),
+            });
+        }
+    }
+    Ok(points)
+}
+
+/// Great circle distance in kilometres.
+fn distance_km(a: &TrackPoint, b: &TrackPoint) -> f64 {
+    let (lat1, lat2) = (a.latitude.to_radians(), b.latitude.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (b.longitude - a.longitude).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+}
+
+fn seconds_between(a: &TrackPoint, b: &TrackPoint) -> Option<f64> {
+    Some((b.time? - a.time?).num_milliseconds() as f64 / 1000.0)
+}
+
+/// Sums the descents and counts the runs. Changes in direction smaller than
+/// [`ELEVATION_NOISE_M`] are ignored.
+fn descents(points: &[TrackPoint]) -> (f64, i64) {
+    let mut elevations = points.iter().filter_map(|x| x.elevation);
+    let Some(first) = elevations.next() else {
+        return (0.0, 0);
+    };
+
+    let mut vertical_drop = 0.0;
+    let mut runs = 0;
+    let mut descending = false;
+    // Where the current descent started, and the lowest point since. While
+    // climbing, `top` is the highest point so far.
+    let mut top = first;
+    let mut bottom = first;
+    for elevation in elevations {
+        if descending {
+            if elevation < bottom {
+                vertical_drop += bottom - elevation;
+                bottom = elevation;
+            } else if elevation - bottom >= ELEVATION_NOISE_M {
+                descending = false;
+                if top - bottom >= MIN_RUN_DROP_M {
+                    runs += 1;
+                }
+                top = elevation;
+            }
+        } else if elevation > top {
+            top = elevation;
+        } else if top - elevation >= ELEVATION_NOISE_M {
+            descending = true;
+            vertical_drop += top - elevation;
+            bottom = elevation;
+        }
+    }
+    if descending && top - bottom >= MIN_RUN_DROP_M {
+        runs += 1;
+    }
+    (vertical_drop, runs)
+}
+
+/// The highest average speed over any stretch of at least
+/// [`MIN_SPEED_WINDOW_SECONDS`].
+fn max_speed_kmh(points: &[TrackPoint]) -> f64 {
+    let mut travelled = vec![0.0];
+    for pair in points.windows(2) {
+        travelled.push(travelled[travelled.len() - 1] + distance_km(&pair[0], &pair[1]));
+    }
+
+    let mut max: f64 = 0.0;
+    let mut start = 0;
+    for end in 1..points.len() {
+        // Use the shortest stretch ending here that is still long enough.
+        while start + 1 < end
+            && seconds_between(&points[start + 1], &points[end])
+                .is_some_and(|seconds| seconds >= MIN_SPEED_WINDOW_SECONDS)
+        {
+            start += 1;
+        }
+        if let Some(seconds) = seconds_between(&points[start], &points[end]) {
+            if seconds >= MIN_SPEED_WINDOW_SECONDS {
+                let distance = travelled[end] - travelled[start];
+                max = max.max(distance / (seconds / 3600.0));
+            }
+        }
+    }
+    max
+}
+
+pub fn summarize(points: &[TrackPoint]) -> TrackSummary {
+    let distance = points.windows(2).map(|x| distance_km(&x[0], &x[1])).sum();
+    let (vertical_drop, runs) = descents(points);
+
+    let start = points.iter().find_map(|x| x.time);
+    let end = points.iter().rev().find_map(|x| x.time);
+    let duration_hours = match (start, end) {
+        (Some(start), Some(end)) if end > start => {
+            Some((end - start).num_seconds() as f64 / 3600.0)
+        }
+        _ => None,
+    };
+
+    TrackSummary {
+        start,
+        duration_hours,
+        stats: TrackStats {
+            distance_km: distance,
+            vertical_drop_m: vertical_drop,
+            max_speed_kmh: max_speed_kmh(points),
+            runs,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gpx(points: &[(f64, f64, f64, &str)]) -> String {
+        let points: String = points
+            .iter()
+            .map(|(lat, lon, ele, time)| {
+                format!(
+                    r#"<trkpt lat="{lat}" lon="{lon}"><ele>{ele}</ele><time>{time}</time></trkpt>"#
+                )
+            })
+            .collect();
+        format!(
+            r#"<?xml version="1.0"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+<trk><trkseg>{points}</trkseg></trk>
+</gpx>"#
+        )
+    }
+
+    #[test]
+    fn gpx_tracks_are_summarized() {
+        let data = gpx(&[
+            (60.0, 8.0, 1400.0, "2025-01-05T10:00:00Z"),
+            (60.0, 8.001, 1402.0, "2025-01-05T10:00:10Z"),
+            (60.001, 8.0, 1300.0, "2025-01-05T10:00:20Z"),
+            (60.002, 8.0, 1200.0, "2025-01-05T10:00:30Z"),
+            (60.0, 8.0, 1400.0, "2025-01-05T10:30:00Z"),
+            (60.002, 8.0, 1340.0, "2025-01-05T11:00:00Z"),
+            (60.002, 8.0, 1330.0, "2025-01-05T12:00:00Z"),
+        ]);
+        let points = parse_track(data.as_bytes()).unwrap();
+        assert_eq!(7, points.len());
+
+        let summary = summarize(&points);
+        assert_eq!(Some(2.0), summary.duration_hours);
+        assert_eq!(2, summary.stats.runs);
+        assert_eq!(272.0, summary.stats.vertical_drop_m.round());
+        // About 124 metres diagonally in 10 seconds.
+        assert!((44.0..46.0).contains(&summary.stats.max_speed_kmh));
+        assert!(summary.stats.distance_km > 0.6);
+        assert_eq!(
+            Some("2025-01-05 10:00:00 UTC".to_owned()),
+            summary.start.map(|x| x.to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_tracks_are_rejected() {
+        assert!(parse_track(b"not a track").is_err());
+        assert!(parse_track(gpx(&[]).as_bytes()).is_err());
+        let mut fit = vec![14, 16, 0, 0, 0, 0, 0, 0];
+        fit.extend_from_slice(b".FIT");
+        assert!(parse_track(&fit).is_err());
+    }
+}
@@ -0,0 +1,783 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Activity, ActivityFilter, ActivityType, EntryTemplate, Location};
+
+const MAX_DURATION_HOURS: f64 = 24.0;
+const MAX_TYPE_LENGTH: usize = 50;
+const MAX_LOCATION_LENGTH: usize = 100;
+const MAX_DAYS_IN_FUTURE: i64 = 7;
+const MAX_COUNTRY_LENGTH: usize = 100;
+const MAX_ICON_LENGTH: usize = 4;
+const MAX_TEMPLATE_NAME_LENGTH: usize = 50;
+
+/// Error messages keyed by form field name.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct FieldErrors(BTreeMap<&'static str, String>);
+
+impl FieldErrors {
+    pub fn add(&mut self, field: &'static str, message: impl Into<String>) {
+        self.0.entry(field).or_insert_with(|| message.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn into_result(self) -> Result<(), FieldErrors> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for FieldErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<String> = self
+            .0
+            .iter()
+            .map(|(field, message)| format!("{}: {}", field, message))
+            .collect();
+        write!(f, "{}", messages.join(", "))
+    }
+}
+
+/// The edit form as submitted by the browser. Every field is kept as text so
+/// the form can be shown again exactly as the user typed it.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivityForm {
+    pub id: Option<i64>,
+    pub date: String,
+    pub location: String,
+    pub duration_hours: String,
+    pub r#type: String,
+    pub score: String,
+    pub description: String,
+    /// An uploaded track that will be attached when the activity is saved.
+    pub track_id: Option<i64>,
+}
+
+impl From<&Activity> for ActivityForm {
+    fn from(value: &Activity) -> Self {
+        ActivityForm {
+            id: value.id,
+            date: value.date.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            location: value.location.clone(),
+            duration_hours: value
+                .duration_hours
+                .map(|x| x.to_string())
+                .unwrap_or_default(),
+            r#type: value.r#type.clone(),
+            score: value.score.map(|x| x.to_string()).unwrap_or_default(),
+            description: value.description.clone(),
+            track_id: None,
+        }
+    }
+}
+
+impl ActivityForm {
+    /// Converts the submitted text into an [`Activity`] and validates it.
+    pub fn parse(&self, now: NaiveDateTime) -> Result<Activity, FieldErrors> {
+        let mut errors = FieldErrors::default();
+
+        let date = parse_date(self.date.trim());
+        if date.is_none() {
+            errors.add("date", "Not a valid date and time");
+        }
+        let duration_hours = parse_optional_float(&self.duration_hours);
+        if duration_hours.is_err() {
+            errors.add("duration_hours", "Must be a number");
+        }
+        let score = parse_optional_float(&self.score);
+        if score.is_err() {
+            errors.add("score", "Must be a number");
+        }
+
+        let activity = Activity {
+            id: self.id,
+            date: date.unwrap_or_default(),
+            location: self.location.trim().to_owned(),
+            duration_hours: duration_hours.unwrap_or_default(),
+            r#type: self.r#type.trim().to_owned(),
+            type_color: None,
+            type_icon: None,
+            score: score.unwrap_or_default(),
+            description: self.description.replace("\r\n", "\n"),
+            track: None,
+        };
+
+        if let Err(more) = activity.validate(now) {
+            for (field, message) in more.0 {
+                errors.add(field, message);
+            }
+        }
+
+        errors.into_result().map(|_| activity)
+    }
+}
+
+impl Activity {
+    /// Checks that the values are within the ranges the rest of the app expects.
+    pub fn validate(&self, now: NaiveDateTime) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::default();
+
+        let earliest = NaiveDate::from_ymd_opt(1900, 1, 1)
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .expect("Valid date");
+        if self.date < earliest {
+            errors.add("date", "Must be after 1900");
+        } else if self.date > now + Duration::days(MAX_DAYS_IN_FUTURE) {
+            errors.add("date", "Can't be more than a week into the future");
+        }
+
+        match self.duration_hours {
+            None => errors.add("duration_hours", "Duration is required"),
+            Some(d) if !d.is_finite() || d <= 0.0 => {
+                errors.add("duration_hours", "Must be more than 0 hours")
+            }
+            Some(d) if d > MAX_DURATION_HOURS => errors.add(
+                "duration_hours",
+                format!("Can't be more than {} hours", MAX_DURATION_HOURS),
+            ),
+            Some(_) => {}
+        }
+
+        if let Some(score) = self.score {
+            if !(0.0..=1.0).contains(&score) {
+                errors.add("score", "Must be between 0 and 1");
+            }
+        }
+
+        let r#type = self.r#type.trim();
+        if r#type.is_empty() {
+            errors.add("type", "Type is required");
+        } else if r#type.chars().count() > MAX_TYPE_LENGTH {
+            errors.add(
+                "type",
+                format!("Can't be longer than {} characters", MAX_TYPE_LENGTH),
+            );
+        }
+
+        if self.location.chars().count() > MAX_LOCATION_LENGTH {
+            errors.add(
+                "location",
+                format!("Can't be longer than {} characters", MAX_LOCATION_LENGTH),
+            );
+        }
+
+        errors.into_result()
+    }
+}
+
+/// The location form, kept as text like [`ActivityForm`].
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocationForm {
+    pub id: Option<i64>,
+    pub name: String,
+    pub latitude: String,
+    pub longitude: String,
+    pub elevation_m: String,
+    pub country: String,
+    pub notes: String,
+}
+
+impl From<&Location> for LocationForm {
+    fn from(value: &Location) -> Self {
+        let text = |x: Option<f64>| x.map(|x| x.to_string()).unwrap_or_default();
+        LocationForm {
+            id: value.id,
+            name: value.name.clone(),
+            latitude: text(value.latitude),
+            longitude: text(value.longitude),
+            elevation_m: text(value.elevation_m),
+            country: value.country.clone(),
+            notes: value.notes.clone(),
+        }
+    }
+}
+
+impl LocationForm {
+    pub fn parse(&self) -> Result<Location, FieldErrors> {
+        let mut errors = FieldErrors::default();
+
+        let name = self.name.trim();
+        if name.is_empty() {
+            errors.add("name", "Name is required");
+        } else if name.chars().count() > MAX_LOCATION_LENGTH {
+            errors.add(
+                "name",
+                format!("Can't be longer than {} characters", MAX_LOCATION_LENGTH),
+            );
+        }
+
+        let mut coordinate =
+            |field: &'static str, value: &str, limit: f64| match parse_optional_float(value) {
+                Ok(Some(x)) if !(-limit..=limit).contains(&x) => {
+                    errors.add(field, format!("Must be between -{} and {}", limit, limit));
+                    None
+                }
+                Ok(x) => x,
+                Err(_) => {
+                    errors.add(field, "Must be a number");
+                    None
+                }
+            };
+        let latitude = coordinate("latitude", &self.latitude, 90.0);
+        let longitude = coordinate("longitude", &self.longitude, 180.0);
+
+        let elevation_m = parse_optional_float(&self.elevation_m).unwrap_or_else(|_| {
+            errors.add("elevation_m", "Must be a number");
+            None
+        });
+        if latitude.is_some() != longitude.is_some() {
+            errors.add("longitude", "Give both latitude and longitude, or neither");
+        }
+
+        let country = self.country.trim();
+        if country.chars().count() > MAX_COUNTRY_LENGTH {
+            errors.add(
+                "country",
+                format!("Can't be longer than {} characters", MAX_COUNTRY_LENGTH),
+            );
+        }
+
+        errors.into_result().map(|_| Location {
+            id: self.id,
+            name: name.to_owned(),
+            latitude,
+            longitude,
+            elevation_m,
+            country: country.to_owned(),
+            notes: self.notes.replace("\r\n", "\n"),
+        })
+    }
+}
+
+/// The type form. `archived` is a checkbox, so it's only sent when checked.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivityTypeForm {
+    pub id: Option<i64>,
+    pub name: String,
+    pub color: String,
+    pub icon: String,
+    pub sort_order: String,
+    pub archived: Option<String>,
+}
+
+impl From<&ActivityType> for ActivityTypeForm {
+    fn from(value: &ActivityType) -> Self {
+        ActivityTypeForm {
+            id: value.id,
+            name: value.name.clone(),
+            color: value.color.clone(),
+            icon: value.icon.clone(),
+            sort_order: value.sort_order.to_string(),
+            archived: value.archived.then(|| "on".to_owned()),
+        }
+    }
+}
+
+impl ActivityTypeForm {
+    pub fn parse(&self) -> Result<ActivityType, FieldErrors> {
+        let mut errors = FieldErrors::default();
+
+        let name = self.name.trim();
+        if name.is_empty() {
+            errors.add("name", "Name is required");
+        } else if name.chars().count() > MAX_TYPE_LENGTH {
+            errors.add(
+                "name",
+                format!("Can't be longer than {} characters", MAX_TYPE_LENGTH),
+            );
+        }
+
+        let color = self.color.trim();
+        let is_hex = color.len() == 7
+            && color.starts_with('#')
+            && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if !is_hex {
+            errors.add("color", "Must be a colour like #1A2B3C");
+        }
+
+        let icon = self.icon.trim();
+        if icon.chars().count() > MAX_ICON_LENGTH {
+            errors.add(
+                "icon",
+                format!("Can't be longer than {} characters", MAX_ICON_LENGTH),
+            );
+        }
+
+        let sort_order = self.sort_order.trim();
+        let sort_order = if sort_order.is_empty() {
+            0
+        } else {
+            sort_order.parse().unwrap_or_else(|_| {
+                errors.add("sort_order", "Must be a whole number");
+                0
+            })
+        };
+
+        errors.into_result().map(|_| ActivityType {
+            id: self.id,
+            name: name.to_owned(),
+            color: color.to_uppercase(),
+            icon: icon.to_owned(),
+            sort_order,
+            archived: self.archived.is_some(),
+        })
+    }
+}
+
+/// The entry template form. Unlike activities, every field but the name can
+/// be left empty.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntryTemplateForm {
+    pub id: Option<i64>,
+    pub name: String,
+    pub r#type: String,
+    pub location: String,
+    pub duration_hours: String,
+    pub description: String,
+    pub is_default: Option<String>,
+}
+
+impl From<&EntryTemplate> for EntryTemplateForm {
+    fn from(value: &EntryTemplate) -> Self {
+        EntryTemplateForm {
+            id: value.id,
+            name: value.name.clone(),
+            r#type: value.r#type.clone(),
+            location: value.location.clone(),
+            duration_hours: value
+                .duration_hours
+                .map(|x| x.to_string())
+                .unwrap_or_default(),
+            description: value.description.clone(),
+            is_default: value.is_default.then(|| "on".to_owned()),
+        }
+    }
+}
+
+impl EntryTemplateForm {
+    pub fn parse(&self) -> Result<EntryTemplate, FieldErrors> {
+        let mut errors = FieldErrors::default();
+
+        let name = self.name.trim();
+        if name.is_empty() {
+            errors.add("name", "Name is required");
+        } else if name.chars().count() > MAX_TEMPLATE_NAME_LENGTH {
+            errors.add(
+                "name",
+                format!(
+                    "Can't be longer than {} characters",
+                    MAX_TEMPLATE_NAME_LENGTH
+                ),
+            );
+        }
+
+        let r#type = self.r#type.trim();
+        if r#type.chars().count() > MAX_TYPE_LENGTH {
+            errors.add(
+                "type",
+                format!("Can't be longer than {} characters", MAX_TYPE_LENGTH),
+            );
+        }
+        let location = self.location.trim();
+        if location.chars().count() > MAX_LOCATION_LENGTH {
+            errors.add(
+                "location",
+                format!("Can't be longer than {} characters", MAX_LOCATION_LENGTH),
+            );
+        }
+
+        let duration_hours = match parse_optional_float(&self.duration_hours) {
+            Ok(Some(d)) if !d.is_finite() || d <= 0.0 || d > MAX_DURATION_HOURS => {
+                errors.add(
+                    "duration_hours",
+                    format!("Must be between 0 and {} hours", MAX_DURATION_HOURS),
+                );
+                None
+            }
+            Ok(d) => d,
+            Err(_) => {
+                errors.add("duration_hours", "Must be a number");
+                None
+            }
+        };
+
+        errors.into_result().map(|_| EntryTemplate {
+            id: self.id,
+            name: name.to_owned(),
+            r#type: r#type.to_owned(),
+            location: location.to_owned(),
+            duration_hours,
+            description: self.description.replace("\r\n", "\n"),
+            is_default: self.is_default.is_some(),
+        })
+    }
+}
+
+/// The filter bar on the activity list. It's sent as query parameters so the
+/// URL can be shared, and `type` and `location` can be given more than once.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterForm {
+    #[serde(rename = "type")]
+    pub types: Vec<String>,
+    #[serde(rename = "location")]
+    pub locations: Vec<String>,
+    pub min_score: String,
+    pub max_score: String,
+    pub min_duration: String,
+    pub max_duration: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// A parsed [`FilterForm`]. The dates are inclusive and replace the season
+/// when given.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Filter {
+    pub activities: ActivityFilter,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl Filter {
+    pub fn has_dates(&self) -> bool {
+        self.from.is_some() || self.to.is_some()
+    }
+
+    /// The range to pass to [`crate::models::get_activities_from`], with an
+    /// exclusive end. Either side is open when its date is not given.
+    pub fn range(&self) -> (Option<NaiveDateTime>, Option<NaiveDateTime>) {
+        let from = self.from.map(|x| x.and_time(NaiveTime::MIN));
+        let to = self
+            .to
+            .and_then(|x| x.succ_opt())
+            .map(|x| x.and_time(NaiveTime::MIN));
+        (from, to)
+    }
+}
+
+impl FilterForm {
+    pub fn is_empty(&self) -> bool {
+        self.query_string().is_empty()
+    }
+
+    /// The non-empty fields as a query string, for links that keep the filter.
+    pub fn query_string(&self) -> String {
+        let mut pairs: Vec<(&str, &str)> = vec![];
+        pairs.extend(self.types.iter().map(|x| ("type", x.as_str())));
+        pairs.extend(self.locations.iter().map(|x| ("location", x.as_str())));
+        pairs.extend([
+            ("min_score", self.min_score.as_str()),
+            ("max_score", self.max_score.as_str()),
+            ("min_duration", self.min_duration.as_str()),
+            ("max_duration", self.max_duration.as_str()),
+            ("from", self.from.as_str()),
+            ("to", self.to.as_str()),
+        ]);
+        pairs.retain(|(_, value)| !value.trim().is_empty());
+        serde_html_form::to_string(pairs).expect("Pairs of strings serialize")
+    }
+
+    pub fn parse(&self) -> Result<Filter, FieldErrors> {
+        let mut errors = FieldErrors::default();
+
+        let mut range =
+            |min_field: &'static str, max_field: &'static str, min: &str, max: &str, limit: f64| {
+                let mut bound = |field: &'static str, value: &str| match parse_optional_float(value)
+                {
+                    Ok(Some(x)) if !(0.0..=limit).contains(&x) => {
+                        errors.add(field, format!("Must be between 0 and {}", limit));
+                        None
+                    }
+                    Ok(x) => x,
+                    Err(_) => {
+                        errors.add(field, "Must be a number");
+                        None
+                    }
+                };
+                let (min, max) = (bound(min_field, min), bound(max_field, max));
+                if let (Some(min), Some(max)) = (min, max) {
+                    if min > max {
+                        errors.add(max_field, "Can't be less than the minimum");
+                    }
+                }
+                (min, max)
+            };
+        let (min_score, max_score) = range(
+            "min_score",
+            "max_score",
+            &self.min_score,
+            &self.max_score,
+            1.0,
+        );
+        let (min_duration, max_duration) = range(
+            "min_duration",
+            "max_duration",
+            &self.min_duration,
+            &self.max_duration,
+            MAX_DURATION_HOURS,
+        );
+
+        let mut date = |field: &'static str, value: &str| {
+            let value = value.trim();
+            if value.is_empty() {
+                return None;
+            }
+            let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok();
+            if date.is_none() {
+                errors.add(field, "Not a valid date");
+            }
+            date
+        };
+        let from = date("from", &self.from);
+        let to = date("to", &self.to);
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                errors.add("to", "Can't be before the start");
+            }
+        }
+
+        let names = |values: &[String]| -> Vec<String> {
+            values
+                .iter()
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_owned())
+                .collect()
+        };
+
+        errors.into_result().map(|_| Filter {
+            activities: ActivityFilter {
+                types: names(&self.types),
+                locations: names(&self.locations),
+                min_score,
+                max_score,
+                min_duration,
+                max_duration,
+            },
+            from,
+            to,
+        })
+    }
+}
+
+fn parse_date(value: &str) -> Option<NaiveDateTime> {
+    [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+}
+
+fn parse_optional_float(value: &str) -> Result<Option<f64>, std::num::ParseFloatError> {
+    let value = value.trim();
+    if value.is_empty() {
+        Ok(None)
+    } else {
+        value.replace(',', ".").parse().map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn get<'a>(errors: &'a FieldErrors, field: &str) -> Option<&'a str> {
+        errors.0.get(field).map(|x| x.as_str())
+    }
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::from_str("2025-01-10T12:00:00").unwrap()
+    }
+
+    fn form() -> ActivityForm {
+        ActivityForm {
+            id: None,
+            date: "2025-01-05T10:00:00".into(),
+            location: " Hemsedal ".into(),
+            duration_hours: "3,5".into(),
+            r#type: "Skis".into(),
+            score: "0.8".into(),
+            description: "Fun".into(),
+            track_id: None,
+        }
+    }
+
+    #[test]
+    fn valid_form_is_parsed() {
+        let activity = form().parse(now()).unwrap();
+        assert_eq!(Some(3.5), activity.duration_hours);
+        assert_eq!(Some(0.8), activity.score);
+        assert_eq!("Hemsedal", activity.location);
+    }
+
+    #[test]
+    fn invalid_fields_are_reported() {
+        let form = ActivityForm {
+            date: "tomorrow".into(),
+            duration_hours: "-1".into(),
+            r#type: "  ".into(),
+            score: "1.5".into(),
+            ..form()
+        };
+        let errors = form.parse(now()).unwrap_err();
+        assert_eq!(Some("Not a valid date and time"), get(&errors, "date"));
+        assert_eq!(
+            Some("Must be more than 0 hours"),
+            get(&errors, "duration_hours")
+        );
+        assert_eq!(Some("Type is required"), get(&errors, "type"));
+        assert_eq!(Some("Must be between 0 and 1"), get(&errors, "score"));
+        assert_eq!(None, get(&errors, "location"));
+    }
+
+    #[test]
+    fn location_coordinates_are_checked() {
+        let form = LocationForm {
+            name: " Hemsedal ".into(),
+            latitude: "60,86".into(),
+            longitude: "8.5".into(),
+            elevation_m: "1450".into(),
+            ..LocationForm::default()
+        };
+        let location = form.parse().unwrap();
+        assert_eq!("Hemsedal", location.name);
+        assert_eq!(Some(60.86), location.latitude);
+
+        let form = LocationForm {
+            name: "".into(),
+            latitude: "91".into(),
+            longitude: "".into(),
+            elevation_m: "high".into(),
+            ..LocationForm::default()
+        };
+        let errors = form.parse().unwrap_err();
+        assert_eq!(Some("Name is required"), get(&errors, "name"));
+        assert_eq!(Some("Must be between -90 and 90"), get(&errors, "latitude"));
+        assert_eq!(Some("Must be a number"), get(&errors, "elevation_m"));
+        assert!(get(&errors, "longitude").is_none());
+    }
+
+    #[test]
+    fn type_colours_are_checked() {
+        let form = ActivityTypeForm {
+            name: "Skis".into(),
+            color: "#a0b1c2".into(),
+            icon: "⛷".into(),
+            sort_order: "2".into(),
+            archived: Some("on".into()),
+            ..ActivityTypeForm::default()
+        };
+        let activity_type = form.parse().unwrap();
+        assert_eq!("#A0B1C2", activity_type.color);
+        assert!(activity_type.archived);
+
+        let form = ActivityTypeForm {
+            color: "red".into(),
+            sort_order: "first".into(),
+            ..form
+        };
+        let errors = form.parse().unwrap_err();
+        assert!(get(&errors, "color").is_some());
+        assert_eq!(Some("Must be a whole number"), get(&errors, "sort_order"));
+    }
+
+    #[test]
+    fn entry_templates_only_need_a_name() {
+        let form = EntryTemplateForm {
+            name: " After work ".into(),
+            ..EntryTemplateForm::default()
+        };
+        let template = form.parse().unwrap();
+        assert_eq!("After work", template.name);
+        assert_eq!(None, template.duration_hours);
+        assert!(!template.is_default);
+
+        let form = EntryTemplateForm {
+            name: "".into(),
+            duration_hours: "30".into(),
+            ..form
+        };
+        let errors = form.parse().unwrap_err();
+        assert_eq!(Some("Name is required"), get(&errors, "name"));
+        assert!(get(&errors, "duration_hours").is_some());
+    }
+
+    #[test]
+    fn dates_far_in_the_future_are_rejected() {
+        let form = ActivityForm {
+            date: "2026-01-05T10:00".into(),
+            ..form()
+        };
+        let errors = form.parse(now()).unwrap_err();
+        assert!(get(&errors, "date").is_some());
+    }
+
+    #[test]
+    fn filters_round_trip_through_the_query_string() {
+        let query = "type=Skis&type=Snow+board&min_score=0.5&max_duration=&from=2025-01-01";
+        let form: FilterForm = serde_html_form::from_str(query).unwrap();
+        assert_eq!(
+            "type=Skis&type=Snow+board&min_score=0.5&from=2025-01-01",
+            form.query_string()
+        );
+
+        let filter = form.parse().unwrap();
+        assert_eq!(vec!["Skis", "Snow board"], filter.activities.types);
+        assert_eq!(Some(0.5), filter.activities.min_score);
+        assert_eq!(None, filter.activities.max_duration);
+        let (from, to) = filter.range();
+        assert_eq!(
+            Some(NaiveDateTime::from_str("2025-01-01T00:00:00").unwrap()),
+            from
+        );
+        assert_eq!(None, to);
+
+        let form = FilterForm {
+            to: "1969-12-31".into(),
+            ..FilterForm::default()
+        };
+        let (from, to) = form.parse().unwrap().range();
+        assert_eq!(None, from);
+        assert_eq!(
+            Some(NaiveDateTime::from_str("1970-01-01T00:00:00").unwrap()),
+            to
+        );
+
+        let form = FilterForm {
+            min_score: "0.8".into(),
+            max_score: "0.2".into(),
+            min_duration: "many".into(),
+            from: "2025-02-01".into(),
+            to: "2025-01-01".into(),
+            ..FilterForm::default()
+        };
+        let errors = form.parse().unwrap_err();
+        for field in ["max_score", "min_duration", "to"] {
+            assert!(get(&errors, field).is_some(), "{}", field);
+        }
+    }
+}